@@ -1,4 +1,5 @@
 mod expand;
+mod registry;
 
 use proc_macro::TokenStream;
 
@@ -6,7 +7,7 @@ use proc_macro::TokenStream;
  * Generates associated c-style structs for c-style struct enum variants.
  *
  * Example:
- * ```
+ * ```ignore
  * #[expand]
  * #[derive(Debug)]
  * enum Enum {
@@ -15,7 +16,7 @@ use proc_macro::TokenStream;
  * }
  * ```
  * generates
- * ```
+ * ```ignore
  * #[derive(Debug)]
  * Foo {
  *     foo: u8
@@ -36,3 +37,59 @@ use proc_macro::TokenStream;
 pub fn expand(_arg_tokens: TokenStream, item_tokens: TokenStream) -> TokenStream {
     crate::expand::expand(item_tokens)
 }
+
+/**
+ * Derives a registry of the variants of a command enum, which must have `CommandSpec` and `ArgSpec` in scope and be
+ * deserialized with `rename_all = "snake_case"`. Each variant gets a spec with its snake_case name, the text of its doc
+ * comment as its description, the names and types of its fields as its args, and the modes given by its `modes`
+ * attribute as its default modes. The specs can be looked up by value with `spec()` and `name()`.
+ *
+ * Example:
+ * ```ignore
+ * #[derive(Registry)]
+ * enum Command {
+ *     /// Scroll down by count rows
+ *     ScrollDown { count: usize },
+ *
+ *     /// Close the prompt
+ *     #[modes("search")]
+ *     Close,
+ * }
+ * ```
+ * generates
+ * ```ignore
+ * impl Command {
+ *     pub const SPECS: &'static [CommandSpec] = &[
+ *         CommandSpec {
+ *             name: "scroll_down",
+ *             description: "Scroll down by count rows",
+ *             args: &[ArgSpec { name: "count", ty: "usize" }],
+ *             modes: &[],
+ *         },
+ *         CommandSpec {
+ *             name: "close",
+ *             description: "Close the prompt",
+ *             args: &[],
+ *             modes: &["search"],
+ *         },
+ *     ];
+ *
+ *     pub fn spec(&self) -> &'static CommandSpec {
+ *         let spec_idx = match self {
+ *             Self::ScrollDown { .. } => 0,
+ *             Self::Close => 1,
+ *         };
+ *
+ *         &Self::SPECS[spec_idx]
+ *     }
+ *
+ *     pub fn name(&self) -> &'static str {
+ *         self.spec().name
+ *     }
+ * }
+ * ```
+ */
+#[proc_macro_derive(Registry, attributes(modes))]
+pub fn registry(item_tokens: TokenStream) -> TokenStream {
+    crate::registry::derive_registry(item_tokens)
+}
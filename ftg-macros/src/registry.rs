@@ -0,0 +1,137 @@
+use proc_macro::TokenStream;
+use quote::ToTokens;
+use syn::{punctuated::Punctuated, Attribute, Data, DeriveInput, Expr, Fields, Lit, LitStr, Meta, Token};
+
+const DOC_ATTRIBUTE: &str = "doc";
+const MODES_ATTRIBUTE: &str = "modes";
+
+// NOTE: matches serde's `rename_all = "snake_case"` for variant names
+fn snake_case(ident: &str) -> String {
+    let mut snake_case = String::new();
+
+    for (idx, chr) in ident.chars().enumerate() {
+        if chr.is_uppercase() && idx != 0 {
+            snake_case.push('_');
+        }
+
+        snake_case.extend(chr.to_lowercase());
+    }
+
+    snake_case
+}
+
+// NOTE: doc comments are desugared into `#[doc = "..."]` attributes, one per line
+fn description(attributes: &[Attribute]) -> String {
+    let mut lines = std::vec![];
+
+    for attribute in attributes {
+        let Meta::NameValue(meta_name_value) = &attribute.meta else {
+            continue;
+        };
+        let Expr::Lit(expr_lit) = &meta_name_value.value else {
+            continue;
+        };
+        let Lit::Str(lit_str) = &expr_lit.lit else {
+            continue;
+        };
+
+        if meta_name_value.path.is_ident(DOC_ATTRIBUTE) {
+            lines.push(lit_str.value().trim().to_owned());
+        }
+    }
+
+    lines.join(" ")
+}
+
+fn modes(attributes: &[Attribute]) -> syn::Result<Vec<LitStr>> {
+    let mut modes = std::vec![];
+
+    for attribute in attributes {
+        if attribute.path().is_ident(MODES_ATTRIBUTE) {
+            modes.extend(attribute.parse_args_with(Punctuated::<LitStr, Token![,]>::parse_terminated)?);
+        }
+    }
+
+    Ok(modes)
+}
+
+fn registry(derive_input: &DeriveInput) -> syn::Result<proc_macro2::TokenStream> {
+    let Data::Enum(data_enum) = &derive_input.data else {
+        return Err(syn::Error::new_spanned(
+            derive_input,
+            "Registry can only be derived for enums",
+        ));
+    };
+    let enum_ident = &derive_input.ident;
+    let mut specs = std::vec![];
+    let mut spec_arms = std::vec![];
+
+    for (spec_idx, variant) in data_enum.variants.iter().enumerate() {
+        let variant_ident = &variant.ident;
+        let name = snake_case(&variant_ident.to_string());
+        let description = description(&variant.attrs);
+        let modes = modes(&variant.attrs)?;
+        let args = match &variant.fields {
+            Fields::Named(fields_named) => fields_named
+                .named
+                .iter()
+                .map(|field| {
+                    let arg_name = field.ident.as_ref().map(ToString::to_string).unwrap_or_default();
+                    let arg_ty = field.ty.to_token_stream().to_string();
+
+                    quote::quote! { ArgSpec { name: #arg_name, ty: #arg_ty } }
+                })
+                .collect(),
+            Fields::Unit => std::vec![],
+            Fields::Unnamed(_fields_unnamed) => {
+                return Err(syn::Error::new_spanned(
+                    variant,
+                    "Registry doesn't support tuple variants",
+                ));
+            }
+        };
+        let pattern = match &variant.fields {
+            Fields::Named(_fields_named) => quote::quote! { Self::#variant_ident { .. } },
+            _ => quote::quote! { Self::#variant_ident },
+        };
+
+        specs.push(quote::quote! {
+            CommandSpec {
+                name: #name,
+                description: #description,
+                args: &[#(#args),*],
+                modes: &[#(#modes),*],
+            }
+        });
+        spec_arms.push(quote::quote! { #pattern => #spec_idx });
+    }
+
+    let registry = quote::quote! {
+        impl #enum_ident {
+            pub const SPECS: &'static [CommandSpec] = &[#(#specs),*];
+
+            pub fn spec(&self) -> &'static CommandSpec {
+                let spec_idx = match self {
+                    #(#spec_arms),*
+                };
+
+                &Self::SPECS[spec_idx]
+            }
+
+            pub fn name(&self) -> &'static str {
+                self.spec().name
+            }
+        }
+    };
+
+    Ok(registry)
+}
+
+pub fn derive_registry(item: TokenStream) -> TokenStream {
+    let derive_input = syn::parse_macro_input!(item as DeriveInput);
+
+    match registry(&derive_input) {
+        Ok(token_stream) => token_stream.into(),
+        Err(error) => error.to_compile_error().into(),
+    }
+}
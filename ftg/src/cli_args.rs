@@ -1,10 +1,20 @@
-use crate::{client::Client, error::Error, server::Server, utils::any::Any};
+use crate::{client::Client, editor::keymap::Keymap, error::Error, server::Server, utils::any::Any};
 use clap::{Parser, Subcommand};
 use std::{net::Ipv4Addr, path::PathBuf};
 
+#[derive(Clone, Subcommand)]
+pub enum KeymapCommand {
+    /// List the key bindings that are duplicated or shadowed by later key bindings
+    Check,
+}
+
 #[derive(Clone, Subcommand)]
 pub enum Command {
     Debug,
+    Keymap {
+        #[command(subcommand)]
+        command: KeymapCommand,
+    },
 }
 
 #[derive(Clone, Parser)]
@@ -43,11 +53,29 @@ impl CliArgs {
         ().ok()
     }
 
+    fn check_keymap(&self) -> Result<(), Error> {
+        let config = Server::config(self)?;
+        let conflicts = Keymap::conflicts(&config.keymap, &config.initial_mode());
+
+        if conflicts.is_empty() {
+            std::println!("no conflicting key bindings");
+        }
+
+        for conflict in conflicts {
+            std::println!("{conflict}");
+        }
+
+        ().ok()
+    }
+
     pub async fn run(self) -> Result<(), Error> {
         self.init_tracing()?;
 
         match self.command {
             Some(Command::Debug) => {}
+            Some(Command::Keymap {
+                command: KeymapCommand::Check,
+            }) => self.check_keymap()?,
             None if self.serve_only => Server::serve(&self).await?,
             None => Client::run(self).await?,
         }
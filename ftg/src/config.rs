@@ -1,8 +1,172 @@
-use crate::editor::{color_scheme::ColorScheme, keymap::KeyBinding};
+use crate::{
+    editor::{
+        color_scheme::ColorScheme, command::Command, composite::Composite, keymap::KeyBinding, macros::Macros,
+        mode::Mode, view::scroll_margins::ScrollMargins,
+    },
+    error::Error,
+    utils::any::Any,
+};
+use crossterm::event::Event;
 use serde::Deserialize;
 
 #[derive(Deserialize)]
 pub struct Config {
     pub color_scheme: ColorScheme,
+
+    #[serde(default = "Config::default_modes")]
+    pub modes: Vec<Mode>,
+
     pub keymap: Vec<KeyBinding>,
+
+    #[serde(default = "Config::default_tab_width")]
+    pub tab_width: usize,
+
+    #[serde(default)]
+    pub soft_wrap: bool,
+
+    #[serde(default)]
+    pub wrap_indicator: Option<String>,
+
+    #[serde(default)]
+    pub scroll_margins: ScrollMargins,
+
+    #[serde(default = "Config::default_key_sequence_timeout_ms")]
+    pub key_sequence_timeout_ms: u64,
+
+    #[serde(default, deserialize_with = "KeyBinding::deserialize_optional_key")]
+    pub count_key: Option<Event>,
+
+    #[serde(default)]
+    pub macros: Macros,
+
+    #[serde(default)]
+    pub composites: Vec<Composite>,
+}
+
+impl Config {
+    const DEFAULT_KEY_SEQUENCE_TIMEOUT_MS: u64 = 1000;
+    const DEFAULT_MODE_NAMES: [&'static str; 4] = ["edit", "search", "palette", "help"];
+    const DEFAULT_TAB_WIDTH: usize = 4;
+    const KEYMAP_KEY: &'static str = "keymap:";
+
+    // NOTE: source_name is only used to describe where each key binding was declared
+    pub fn new(source_name: &str, source: &str) -> Result<Self, Error> {
+        let mut config = source.deserialize_from_yaml::<Self>()?;
+        let keymap_lines = Self::keymap_lines(source);
+        let located = keymap_lines.len() == config.keymap.len();
+
+        // NOTE: if the keymap isn't a block sequence then its items can't be found by keymap_lines(), in which case
+        // each key binding is described by its index instead
+        for (idx, key_binding) in config.keymap.iter_mut().enumerate() {
+            let location = if located {
+                std::format!("{source_name}:{}", keymap_lines[idx])
+            } else {
+                std::format!("{source_name}: keymap[{idx}]")
+            };
+
+            key_binding.set_location(location);
+        }
+
+        config.validate_modes()?;
+
+        config.ok()
+    }
+
+    // NOTE: the modes named by key bindings and composite commands, including the modes their commands switch to, must
+    // be declared, while contexts are only warned about since they were replaced by modes
+    fn validate_modes(&self) -> Result<(), Error> {
+        if self.modes.is_empty() {
+            return Error::NoModes.err();
+        }
+
+        for key_binding in &self.keymap {
+            if let Some(contexts) = key_binding.contexts() {
+                tracing::warn!(
+                    key_binding.location = key_binding.location(),
+                    ?contexts,
+                    "contexts is ignored, use modes instead"
+                );
+            }
+
+            let mode_names = key_binding
+                .declared_modes()
+                .iter()
+                .map(String::as_str)
+                .chain(key_binding.command().mode());
+
+            if let Some(mode_name) = self.unknown_mode(mode_names) {
+                return Error::UnknownMode(key_binding.location().to_owned(), mode_name.to_owned()).err();
+            }
+        }
+
+        for composite in &self.composites {
+            let mode_names = composite
+                .declared_modes()
+                .iter()
+                .map(String::as_str)
+                .chain(composite.commands().iter().filter_map(Command::mode));
+
+            if let Some(mode_name) = self.unknown_mode(mode_names) {
+                let location = std::format!("composite command {:?}", composite.name());
+
+                return Error::UnknownMode(location, mode_name.to_owned()).err();
+            }
+        }
+
+        ().ok()
+    }
+
+    fn unknown_mode<'a>(&self, mut mode_names: impl Iterator<Item = &'a str>) -> Option<&'a str> {
+        mode_names.find(|mode_name| self.modes.iter().all(|mode| mode.name() != *mode_name))
+    }
+
+    // NOTE: serde_yaml doesn't expose the location of deserialized values, so instead this returns the line number of
+    // each item of the top-level keymap block sequence by scanning the source for the lines that begin those items
+    fn keymap_lines(source: &str) -> Vec<usize> {
+        let mut keymap_lines = std::vec![];
+        let mut in_keymap = false;
+        let mut item_indent = None;
+
+        for (line_idx, line) in source.lines().enumerate() {
+            let trimmed_line = line.trim_start();
+
+            if trimmed_line.is_empty() || trimmed_line.starts_with('#') {
+                continue;
+            }
+
+            let indent = line.len().saturating_sub(trimmed_line.len());
+            let is_item = trimmed_line.starts_with('-');
+
+            if indent == 0 && !is_item {
+                in_keymap = line.starts_with(Self::KEYMAP_KEY);
+            } else if in_keymap && is_item && *item_indent.get_or_insert(indent) == indent {
+                keymap_lines.push(line_idx.saturating_add(1));
+            }
+        }
+
+        keymap_lines
+    }
+
+    // NOTE: the first mode declared in the config is the mode every view begins in; new() rejects configs without modes
+    pub fn initial_mode(&self) -> String {
+        self.modes
+            .first()
+            .map(|mode| mode.name().to_owned())
+            .unwrap_or_default()
+    }
+
+    fn default_modes() -> Vec<Mode> {
+        Self::DEFAULT_MODE_NAMES
+            .into_iter()
+            .map(|mode_name| Mode::new(mode_name.to_owned(), true))
+            .collect()
+    }
+
+    fn default_key_sequence_timeout_ms() -> u64 {
+        Self::DEFAULT_KEY_SEQUENCE_TIMEOUT_MS
+    }
+
+    fn default_tab_width() -> usize {
+        Self::DEFAULT_TAB_WIDTH
+    }
 }
@@ -1,11 +1,19 @@
 use crate::{
-    editor::{buffer::search::SearchIter, selection::region::Region},
+    editor::{
+        buffer::{
+            grapheme::Grapheme,
+            history::{Change, History},
+            layout::{Layout, SubLine},
+            search::{Matches, Pattern, RegexSearch, SearchOptions},
+        },
+        selection::{region::Region, set::SelectionSet},
+    },
     utils::{any::Any, container::Identifiable, path::Path, position::Position},
 };
-use derive_more::Constructor;
 use ratatui::layout::Rect;
-use ropey::{iter::Chunks, Error as RopeyError, Rope, RopeSlice};
-use std::io::Error as IoError;
+use regex::Error as RegexError;
+use ropey::{iter::Chunks, Error as RopeyError, Rope};
+use std::{io::Error as IoError, ops::Range};
 use ulid::Ulid;
 use unicode_segmentation::UnicodeSegmentation;
 
@@ -15,29 +23,36 @@ pub struct LineCharIndices {
     pub query: usize,
 }
 
-#[derive(Constructor)]
-pub struct SubLine<'a> {
-    slice: RopeSlice<'a>,
-    region: Option<Region>,
-}
-
-impl<'a> SubLine<'a> {
-    pub fn region(&self) -> Option<Region> {
-        self.region
-    }
-
-    pub fn graphemes(&self) -> impl Iterator<Item = &str> {
-        self.slice.chunks().flat_map(|chunk_str| chunk_str.graphemes(true))
-    }
-}
-
-#[derive(Constructor)]
 pub struct Buffer {
     id: Ulid,
     rope: Rope,
+    history: History,
+    dirty: bool,
+    saved_position: Option<usize>,
 }
 
 impl Buffer {
+    const INDENT: &'static str = "    ";
+    const INDENT_TAB: &'static str = "\t";
+    const LINE_BREAK_CHARS: [char; 2] = ['\n', '\r'];
+    // NOTE: \r\n must come before \n and \r, which it ends with and begins with
+    const LINE_ENDINGS: [&'static str; 3] = ["\r\n", "\n", "\r"];
+    const OPENING_BRACKETS: [char; 3] = ['(', '[', '{'];
+
+    pub fn new(id: Ulid, rope: Rope) -> Self {
+        let history = History::default();
+        let dirty = false;
+        let saved_position = history.position();
+
+        Self {
+            id,
+            rope,
+            history,
+            dirty,
+            saved_position,
+        }
+    }
+
     pub fn id(&self) -> Ulid {
         self.id
     }
@@ -46,78 +61,512 @@ impl Buffer {
         Self::new(filepath.inode_id()?, filepath.rope()?).ok()
     }
 
-    pub fn search<'q, 'r>(&'r self, query: &'q str) -> SearchIter<'q, 'r> {
-        SearchIter::new(&self.rope, query)
+    // NOTE: matches are found lazily, so only as much of char_range is searched as the matches that are taken need
+    pub fn search<'p>(&self, pattern: &'p Pattern, char_range: Range<usize>) -> Matches<'p, '_> {
+        pattern.matches(&self.rope, char_range)
     }
 
-    pub fn sub_lines<'a>(&'a self, position: &'a Position, area: Rect) -> impl 'a + Iterator<Item = SubLine<'a>> {
+    // NOTE: returns each match of the query along with the text to replace it with
+    pub fn replacements(
+        &self,
+        query: &str,
+        replacement: &str,
+        options: SearchOptions,
+    ) -> Result<Vec<(Region, String)>, RegexError> {
+        RegexSearch::new(query, options)?
+            .replacements(&self.rope, replacement)
+            .ok()
+    }
+
+    // NOTE: position.x is a display column rather than a char index and position.sub_y is the number of sub lines of
+    // the position.y-th line that are scrolled past
+    pub fn sub_lines<'a>(
+        &'a self,
+        position: &'a Position,
+        area: Rect,
+        layout: &'a Layout,
+    ) -> impl 'a + Iterator<Item = SubLine> {
         // TODO-c8394f:
         // - is there a more efficient way of getting the char_idx of the position.y-th line?
         // - i don't like that i need to call .line_to_char() bc .get_lines_at() doesn't contain that information bc
         //   rope slices themselves don't contain that information (publicly afaict)
         let mut line_char_idx = self.rope.line_to_char(position.y);
-        let begin = position.x;
 
-        // NOTE:
-        // - .into_iter().flatten() to flatten the Option return by .get_lines_at()
-        // - let begin = begin.min(end) in case end has been reduced to 0 because len_chars is 0
+        // NOTE: .into_iter().flatten() to flatten the Option return by .get_lines_at()
         self.rope
             .get_lines_at(position.y)
             .into_iter()
             .flatten()
+            .enumerate()
+            .flat_map(move |(line_idx, line_slice)| {
+                let sub_lines = layout.sub_lines(line_slice, line_char_idx, position.x, area.width.into());
+                let num_skipped = if line_idx == 0 { position.sub_y } else { 0 };
+
+                line_char_idx += line_slice.len_chars();
+
+                sub_lines.into_iter().skip(num_skipped)
+            })
             .take(area.height as usize)
-            .map(move |line_slice| {
-                let len_chars = line_slice.len_chars();
-                let end = begin.saturating_add(area.width as usize).min(len_chars);
-                let begin = begin.min(end);
-                let slice = line_slice.slice(begin..end);
-                let begin = line_char_idx.saturating_add(begin);
-                let end = line_char_idx.saturating_add(end);
-                let region = Region::try_ie(begin, end);
-                let sub_line = SubLine::new(slice, region);
-
-                line_char_idx += len_chars;
-
-                sub_line
+    }
+
+    // NOTE: lays out the entire given row as it would be rendered in an area of the given width
+    pub fn row_sub_lines(&self, row: usize, layout: &Layout, width: usize) -> Vec<SubLine> {
+        let row = row.min(self.rope.len_lines().saturating_sub(1));
+
+        layout.sub_lines(self.rope.line(row), self.rope.line_to_char(row), 0, width)
+    }
+
+    // NOTE: returns the row of char_idx, the index of the sub line of that row containing char_idx, and the display
+    // column of char_idx within that sub line
+    pub fn sub_row_col(&self, char_idx: usize, layout: &Layout, width: usize) -> (usize, usize, usize) {
+        let char_idx = char_idx.min(self.rope.len_chars());
+        let row = self.rope.char_to_line(char_idx);
+
+        for (sub_row, sub_line) in self.row_sub_lines(row, layout, width).iter().enumerate() {
+            if let Some(col) = sub_line.col(char_idx) {
+                return (row, sub_row, col);
+            }
+        }
+
+        (row, 0, 0)
+    }
+
+    // NOTE: sub_row and col will saturate at the last sub line of the given row and at the end of that sub line
+    pub fn sub_row_char_idx(&self, row: usize, sub_row: usize, col: usize, layout: &Layout, width: usize) -> usize {
+        let sub_lines = self.row_sub_lines(row, layout, width);
+        let sub_line = sub_lines.get(sub_row).or_else(|| sub_lines.last());
+
+        sub_line
+            .and_then(|sub_line| sub_line.char_idx(col))
+            .unwrap_or_else(|| self.rope.line_to_char(row.min(self.rope.len_lines().saturating_sub(1))))
+    }
+
+    // NOTE: moves the given (row, sub_row) pair by count sub lines, saturating at the first and last sub lines of the
+    // buffer
+    pub fn add_sub_rows(
+        &self,
+        (mut row, mut sub_row): (usize, usize),
+        count: isize,
+        layout: &Layout,
+        width: usize,
+    ) -> (usize, usize) {
+        let max_row = self.rope.len_lines().saturating_sub(1);
+        let num_sub_rows = |row| self.row_sub_lines(row, layout, width).len();
+
+        if count.is_negative() {
+            for _ in 0..count.unsigned_abs() {
+                if 0 < sub_row {
+                    sub_row -= 1;
+                } else if 0 < row {
+                    row -= 1;
+                    sub_row = num_sub_rows(row).saturating_sub(1);
+                } else {
+                    break;
+                }
+            }
+        } else {
+            let mut num_sub_rows_of_row = num_sub_rows(row);
+
+            for _ in 0..count.unsigned_abs() {
+                if sub_row.saturating_add(1) < num_sub_rows_of_row {
+                    sub_row += 1;
+                } else if row < max_row {
+                    row += 1;
+                    sub_row = 0;
+                    num_sub_rows_of_row = num_sub_rows(row);
+                } else {
+                    break;
+                }
+            }
+        }
+
+        (row, sub_row)
+    }
+
+    // NOTE: returns the display width of the widest of the given rows, including the single cell its line break is laid
+    // out as
+    pub fn max_width(&self, rows: Range<usize>, tab_width: usize) -> usize {
+        let end = rows.end.min(self.rope.len_lines());
+        let begin = rows.start.min(end);
+
+        (begin..end)
+            .map(|row| {
+                let (_row, col) = self.row_col(self.line_end(self.rope.line_to_char(row)), tab_width);
+
+                col.saturating_add(1)
             })
+            .max()
+            .unwrap_or(0)
     }
 
     pub fn len_lines(&self) -> usize {
         self.rope.len_lines()
     }
 
+    pub fn row(&self, char_idx: usize) -> usize {
+        self.rope.char_to_line(char_idx.min(self.rope.len_chars()))
+    }
+
+    // NOTE: returns the char range of the given rows including their line breaks; rows will saturate at the end of the
+    // buffer
+    pub fn rows_char_range(&self, rows: Range<usize>) -> Range<usize> {
+        let len_lines = self.rope.len_lines();
+
+        self.rope.line_to_char(rows.start.min(len_lines))..self.rope.line_to_char(rows.end.min(len_lines))
+    }
+
+    pub fn len_chars(&self) -> usize {
+        self.rope.len_chars()
+    }
+
     pub fn insert_char(&mut self, char_idx: usize, chr: char) -> Result<(), RopeyError> {
-        self.rope.try_insert_char(char_idx, chr)
+        self.rope.try_insert_char(char_idx, chr)?;
+        self.history.push(Change::insert(char_idx, chr.into()));
+
+        self.dirty = true;
+
+        ().ok()
+    }
+
+    // NOTE: char_range will saturate at the end of the buffer
+    pub fn text(&self, char_range: Range<usize>) -> String {
+        let end = char_range.end.min(self.rope.len_chars());
+        let begin = char_range.start.min(end);
+
+        self.rope.slice(begin..end).to_string()
+    }
+
+    pub fn insert(&mut self, char_idx: usize, text: &str) -> Result<(), RopeyError> {
+        self.rope.try_insert(char_idx, text)?;
+        self.history.push(Change::insert(char_idx, text.into()));
+
+        self.dirty = true;
+
+        ().ok()
+    }
+
+    // NOTE:
+    // - returns the text to insert at char_idx to begin a new line: the line ending of the line containing char_idx
+    //   followed by the part of its leading whitespace that is before char_idx, plus one indent level if the last
+    //   non-whitespace char before char_idx on that line is an opening bracket
+    // - the indent level is a tab if the line is indented with tabs and four spaces otherwise
+    pub fn newline(&self, char_idx: usize) -> String {
+        let char_idx = char_idx.min(self.rope.len_chars());
+        let row = self.rope.char_to_line(char_idx);
+        let char_idx_of_line = self.rope.line_to_char(row);
+        let leading_whitespace = self
+            .rope
+            .line(row)
+            .chars()
+            .take(char_idx.saturating_sub(char_idx_of_line))
+            .take_while(|chr| *chr == ' ' || *chr == '\t')
+            .collect::<String>();
+        let after_opening_bracket = self
+            .rope
+            .slice(char_idx_of_line..char_idx)
+            .chars_at(char_idx.saturating_sub(char_idx_of_line))
+            .reversed()
+            .find(|chr| !chr.is_whitespace())
+            .is_some_and(|chr| Self::OPENING_BRACKETS.contains(&chr));
+        let mut newline = std::format!("{}{leading_whitespace}", self.line_ending(row));
+
+        if after_opening_bracket {
+            let indent = if leading_whitespace.starts_with(Self::INDENT_TAB) {
+                Self::INDENT_TAB
+            } else {
+                Self::INDENT
+            };
+
+            newline.push_str(indent);
+        }
+
+        newline
+    }
+
+    // NOTE: the line ending of a row is its own, or that of the row above it if it's the last row and doesn't have one,
+    // or a newline if neither does
+    fn line_ending(&self, row: usize) -> &'static str {
+        [row, row.saturating_sub(1)]
+            .into_iter()
+            .find_map(|row| {
+                let line = self.rope.line(row);
+                let len_chars = line.len_chars();
+
+                Self::LINE_ENDINGS
+                    .into_iter()
+                    .find(|line_ending| line.slice(len_chars.saturating_sub(line_ending.len())..) == *line_ending)
+            })
+            .unwrap_or(Self::LINE_ENDINGS[1])
+    }
+
+    // NOTE: char_range will saturate at the end of the buffer; returns the number of chars that were removed
+    pub fn remove(&mut self, char_range: Range<usize>) -> Result<usize, RopeyError> {
+        let end = char_range.end.min(self.rope.len_chars());
+        let begin = char_range.start.min(end);
+
+        if begin == end {
+            return 0.ok();
+        }
+
+        let removed = self.rope.slice(begin..end).to_string();
+
+        self.rope.try_remove(begin..end)?;
+        self.history.push(Change::remove(begin, removed));
+
+        self.dirty = true;
+
+        end.saturating_sub(begin).ok()
+    }
+
+    // NOTE: groups all changes made since the last commit into a single undoable step
+    pub fn commit(&mut self, selection_set_before: SelectionSet, selection_set_after: SelectionSet) {
+        self.history.commit(selection_set_before, selection_set_after);
+    }
+
+    pub fn undo(&mut self) -> Result<Option<SelectionSet>, RopeyError> {
+        let selection_set = self.history.undo(&mut self.rope)?;
+
+        if selection_set.is_some() {
+            self.dirty = self.history.position() != self.saved_position;
+        }
+
+        selection_set.ok()
+    }
+
+    pub fn redo(&mut self) -> Result<Option<SelectionSet>, RopeyError> {
+        let selection_set = self.history.redo(&mut self.rope)?;
+
+        if selection_set.is_some() {
+            self.dirty = self.history.position() != self.saved_position;
+        }
+
+        selection_set.ok()
+    }
+
+    // NOTE: a buffer is dirty if it has been changed since it was last saved, unless those changes have since been undone
+    // or redone back to the position in the history it was saved at
+    pub fn is_dirty(&self) -> bool {
+        self.dirty
+    }
+
+    pub fn mark_saved(&mut self) {
+        self.dirty = false;
+        self.saved_position = self.history.position();
     }
 
     pub fn chunks(&self) -> Chunks {
         self.rope.chunks()
     }
 
-    pub fn row_col(&self, char_idx: usize) -> (usize, usize) {
+    fn is_blank(&self, row: usize) -> bool {
+        self.rope.line(row).chars().all(char::is_whitespace)
+    }
+
+    // NOTE: returns the char ranges of the words on the given row, where a word is any segment between unicode word
+    // boundaries that is not whitespace
+    fn words(&self, row: usize) -> Vec<Range<usize>> {
+        let mut char_idx = self.rope.line_to_char(row);
+        let mut words = std::vec![];
+
+        for segment in self.rope.line(row).to_string().split_word_bounds() {
+            let begin = char_idx;
+
+            char_idx = char_idx.saturating_add(segment.chars().count());
+
+            if !segment.chars().all(char::is_whitespace) {
+                words.push(begin..char_idx);
+            }
+        }
+
+        words
+    }
+
+    pub fn line_begin(&self, char_idx: usize) -> usize {
+        let row = self.rope.char_to_line(char_idx.min(self.rope.len_chars()));
+
+        self.rope.line_to_char(row)
+    }
+
+    pub fn first_non_blank(&self, char_idx: usize) -> usize {
+        let row = self.rope.char_to_line(char_idx.min(self.rope.len_chars()));
+        let len_leading_whitespace = self
+            .rope
+            .line(row)
+            .chars()
+            .take_while(|chr| *chr == ' ' || *chr == '\t')
+            .count();
+
+        self.rope.line_to_char(row).saturating_add(len_leading_whitespace)
+    }
+
+    // NOTE: returns the char index just after the last char of the line, ie the index of its line break if it has one
+    pub fn line_end(&self, char_idx: usize) -> usize {
+        let row = self.rope.char_to_line(char_idx.min(self.rope.len_chars()));
+        let len_line_break = self
+            .rope
+            .line(row)
+            .chars_at(self.rope.line(row).len_chars())
+            .reversed()
+            .take_while(|chr| Self::LINE_BREAK_CHARS.contains(chr))
+            .count();
+
+        self.rope
+            .line_to_char(row.saturating_add(1))
+            .saturating_sub(len_line_break)
+            .max(self.rope.line_to_char(row))
+    }
+
+    pub fn next_word_start(&self, char_idx: usize) -> usize {
+        let row = self.rope.char_to_line(char_idx.min(self.rope.len_chars()));
+
+        (row..self.rope.len_lines())
+            .flat_map(|row| self.words(row))
+            .map(|word| word.start)
+            .find(|begin| char_idx < *begin)
+            .unwrap_or(self.rope.len_chars())
+    }
+
+    pub fn previous_word_start(&self, char_idx: usize) -> usize {
+        let row = self.rope.char_to_line(char_idx.min(self.rope.len_chars()));
+
+        (0..=row)
+            .rev()
+            .flat_map(|row| self.words(row).into_iter().rev())
+            .map(|word| word.start)
+            .find(|begin| *begin < char_idx)
+            .unwrap_or(0)
+    }
+
+    pub fn word_end(&self, char_idx: usize) -> usize {
+        let row = self.rope.char_to_line(char_idx.min(self.rope.len_chars()));
+
+        (row..self.rope.len_lines())
+            .flat_map(|row| self.words(row))
+            .map(|word| word.end.saturating_sub(1))
+            .find(|last| char_idx < *last)
+            .unwrap_or(self.rope.len_chars())
+    }
+
+    // NOTE: a paragraph boundary is a blank line that follows a non-blank line in the direction of travel
+    pub fn next_paragraph(&self, char_idx: usize) -> usize {
+        let row = self.rope.char_to_line(char_idx.min(self.rope.len_chars()));
+        let mut seen_non_blank = !self.is_blank(row);
+
+        for row in row.saturating_add(1)..self.rope.len_lines() {
+            if !self.is_blank(row) {
+                seen_non_blank = true;
+            } else if seen_non_blank {
+                return self.rope.line_to_char(row);
+            }
+        }
+
+        self.rope.len_chars()
+    }
+
+    pub fn previous_paragraph(&self, char_idx: usize) -> usize {
+        let row = self.rope.char_to_line(char_idx.min(self.rope.len_chars()));
+        let mut seen_non_blank = !self.is_blank(row);
+
+        for row in (0..row).rev() {
+            if !self.is_blank(row) {
+                seen_non_blank = true;
+            } else if seen_non_blank {
+                return self.rope.line_to_char(row);
+            }
+        }
+
+        0
+    }
+
+    // NOTE: returns the char index of the grapheme boundary after char_idx, which is char_idx itself at the end of the
+    // buffer
+    pub fn next_grapheme(&self, char_idx: usize) -> usize {
+        let char_idx = char_idx.min(self.rope.len_chars());
+        let row = self.rope.char_to_line(char_idx);
+        let mut boundary = self.rope.line_to_char(row);
+
+        for grapheme in self.rope.line(row).to_string().graphemes(true) {
+            boundary = boundary.saturating_add(grapheme.chars().count());
+
+            if char_idx < boundary {
+                return boundary;
+            }
+        }
+
+        boundary
+    }
+
+    // NOTE: returns the char index of the grapheme boundary before char_idx, which is char_idx itself at the beginning
+    // of the buffer
+    pub fn previous_grapheme(&self, char_idx: usize) -> usize {
+        let char_idx = char_idx.min(self.rope.len_chars());
+        let Some(prev_char_idx) = char_idx.checked_sub(1) else {
+            return 0;
+        };
+        let row = self.rope.char_to_line(prev_char_idx);
+        let mut boundary = self.rope.line_to_char(row);
+
+        for grapheme in self.rope.line(row).to_string().graphemes(true) {
+            let next_boundary = boundary.saturating_add(grapheme.chars().count());
+
+            if char_idx <= next_boundary {
+                break;
+            }
+
+            boundary = next_boundary;
+        }
+
+        boundary
+    }
+
+    // NOTE: col is the display column of char_idx in its row, ie the number of terminal cells the graphemes before it
+    // occupy
+    pub fn row_col(&self, char_idx: usize, tab_width: usize) -> (usize, usize) {
         // TODO-9ec981:
         // - figure out if this is the most efficient way to do this (calling multiple different rope methods)
         // - TODO-c8394f: feels like this would be obviated if we could get the real index associated w a sub-rope-slice
+        let char_idx = char_idx.min(self.rope.len_chars());
         let row = self.rope.char_to_line(char_idx);
         let char_idx_of_line = self.rope.line_to_char(row);
-        let col = char_idx.saturating_sub(char_idx_of_line);
+        let col = self
+            .rope
+            .slice(char_idx_of_line..char_idx)
+            .to_string()
+            .graphemes(true)
+            .fold(0, |col: usize, grapheme| {
+                col.saturating_add(Grapheme::new(grapheme).width(col, tab_width))
+            });
 
         (row, col)
     }
 
     // NOTE:
     // - row will saturate at the max possible row
-    // - col will saturate at the max possible col for the given row
-    pub fn char_idx(&self, row: usize, col: usize) -> LineCharIndices {
+    // - col is a display column and will saturate at the end of the given row
+    // - if col falls inside of a grapheme (eg a tab or a wide char), query is the char index of that grapheme
+    pub fn char_idx(&self, row: usize, col: usize, tab_width: usize) -> LineCharIndices {
         // TODO-9ec981
         let max_row = self.rope.len_lines().saturating_sub(1);
         let row = row.clamp(0, max_row);
         let char_idx_of_line_begin = self.rope.line_to_char(row);
-        let line_rope_slice = self.rope.line(row);
-        let max_col = line_rope_slice.len_chars().saturating_sub(1);
-        let col = col.clamp(0, max_col);
-        let char_idx = char_idx_of_line_begin.saturating_add(col);
-        let char_idx_of_line_last = char_idx.saturating_add(col);
+        let char_idx_of_line_last = self.line_end(char_idx_of_line_begin);
+        let mut char_idx = char_idx_of_line_begin;
+        let mut display_col: usize = 0;
+
+        for grapheme in self
+            .rope
+            .slice(char_idx_of_line_begin..char_idx_of_line_last)
+            .to_string()
+            .graphemes(true)
+        {
+            display_col = display_col.saturating_add(Grapheme::new(grapheme).width(display_col, tab_width));
+
+            if col < display_col {
+                break;
+            }
+
+            char_idx = char_idx.saturating_add(grapheme.chars().count());
+        }
 
         LineCharIndices {
             begin: char_idx_of_line_begin,
@@ -138,3 +587,37 @@ impl Identifiable for Buffer {
         self.id()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::editor::buffer::buffer::Buffer;
+    use ropey::Rope;
+    use ulid::Ulid;
+
+    fn from_text(text: &str) -> Buffer {
+        Buffer::new(Ulid::new(), Rope::from_str(text))
+    }
+
+    #[test]
+    fn newline_copies_only_the_indent_before_the_cursor() {
+        let buffer = from_text("        foo\n");
+
+        assert_eq!(buffer.newline(0), "\n");
+        assert_eq!(buffer.newline(4), "\n    ");
+        assert_eq!(buffer.newline(8), "\n        ");
+        assert_eq!(buffer.newline(10), "\n        ");
+        assert_eq!(from_text("\t\tfoo {\n").newline(1), "\n\t");
+        assert_eq!(from_text("\tfoo {\n").newline(6), "\n\t\t");
+    }
+
+    #[test]
+    fn newline_reuses_the_line_ending() {
+        let buffer = from_text("foo\r\n  bar\r\nbaz");
+
+        assert_eq!(buffer.newline(2), "\r\n");
+        assert_eq!(buffer.newline(9), "\r\n  ");
+        assert_eq!(buffer.newline(14), "\r\n");
+        assert_eq!(from_text("foo\rbar").newline(1), "\r");
+        assert_eq!(from_text("foo").newline(1), "\n");
+    }
+}
@@ -0,0 +1,58 @@
+use crate::utils::any::Any;
+use std::borrow::Cow;
+use unicode_width::UnicodeWidthStr;
+
+pub struct Grapheme<'a> {
+    value: &'a str,
+}
+
+impl<'a> Grapheme<'a> {
+    const CARET_XOR_MASK: u32 = 0x40;
+    const SPACE: &'static str = " ";
+    const TAB: &'static str = "\t";
+
+    pub fn new(value: &'a str) -> Self {
+        Self { value }
+    }
+
+    // NOTE: a control char is shown in caret notation (eg ^A for U+0001 and ^? for U+007F) if it has one and as its
+    // hex code point otherwise (eg <85> for U+0085)
+    fn escape(&self) -> Option<String> {
+        let mut chars = self.value.chars();
+        let (Some(chr), None) = (chars.next(), chars.next()) else {
+            return None;
+        };
+        let code_point = u32::from(chr);
+
+        match chr {
+            '\u{0}'..='\u{1f}' | '\u{7f}' => {
+                let caret_chr = char::from_u32(code_point ^ Self::CARET_XOR_MASK)?;
+
+                std::format!("^{caret_chr}").some()
+            }
+            _ if chr.is_control() => std::format!("<{code_point:02x}>").some(),
+            _ => None,
+        }
+    }
+
+    // NOTE: returns the text that the grapheme is rendered as when it begins at the given display column; a tab expands
+    // to spaces up to the next tab stop and a control char is escaped so that it is visible
+    pub fn display(&self, col: usize, tab_width: usize) -> Cow<'a, str> {
+        if self.value == Self::TAB {
+            let tab_width = tab_width.max(1);
+            let num_spaces = tab_width.saturating_sub(col % tab_width);
+
+            Self::SPACE.repeat(num_spaces).into()
+        } else if let Some(escape) = self.escape() {
+            escape.into()
+        } else {
+            self.value.into()
+        }
+    }
+
+    // NOTE: the number of terminal cells the grapheme occupies when it begins at the given display column, eg two for
+    // wide CJK chars and emoji
+    pub fn width(&self, col: usize, tab_width: usize) -> usize {
+        self.display(col, tab_width).width()
+    }
+}
@@ -0,0 +1,126 @@
+use crate::{editor::selection::set::SelectionSet, utils::any::Any};
+use ropey::{Error as RopeyError, Rope};
+
+// NOTE: a change replaces the text `removed` beginning at `char_idx` with the text `inserted`; reverting a change is
+// just applying the change with `removed` and `inserted` swapped
+pub struct Change {
+    char_idx: usize,
+    removed: String,
+    inserted: String,
+}
+
+impl Change {
+    pub fn insert(char_idx: usize, inserted: String) -> Self {
+        Self {
+            char_idx,
+            removed: String::new(),
+            inserted,
+        }
+    }
+
+    pub fn remove(char_idx: usize, removed: String) -> Self {
+        Self {
+            char_idx,
+            removed,
+            inserted: String::new(),
+        }
+    }
+
+    fn replace(rope: &mut Rope, char_idx: usize, removed: &str, inserted: &str) -> Result<(), RopeyError> {
+        let end = char_idx.saturating_add(removed.chars().count());
+
+        rope.try_remove(char_idx..end)?;
+        rope.try_insert(char_idx, inserted)?;
+
+        ().ok()
+    }
+
+    pub fn apply(&self, rope: &mut Rope) -> Result<(), RopeyError> {
+        Self::replace(rope, self.char_idx, &self.removed, &self.inserted)
+    }
+
+    pub fn revert(&self, rope: &mut Rope) -> Result<(), RopeyError> {
+        Self::replace(rope, self.char_idx, &self.inserted, &self.removed)
+    }
+}
+
+// NOTE: a step is the group of changes made by a single command along with the selection set of the view that made
+// them both before and after the changes were made; its id is unique within its history
+struct Step {
+    id: usize,
+    changes: Vec<Change>,
+    selection_set_before: SelectionSet,
+    selection_set_after: SelectionSet,
+}
+
+#[derive(Default)]
+pub struct History {
+    pending_changes: Vec<Change>,
+    undo_steps: Vec<Step>,
+    redo_steps: Vec<Step>,
+    num_steps: usize,
+}
+
+impl History {
+    pub fn push(&mut self, change: Change) {
+        self.pending_changes.push(change);
+    }
+
+    pub fn commit(&mut self, selection_set_before: SelectionSet, selection_set_after: SelectionSet) {
+        if self.pending_changes.is_empty() {
+            return;
+        }
+
+        let step = Step {
+            id: self.num_steps,
+            changes: self.pending_changes.mem_take(),
+            selection_set_before,
+            selection_set_after,
+        };
+
+        self.undo_steps.push(step);
+        self.redo_steps.clear();
+
+        self.num_steps = self.num_steps.saturating_add(1);
+    }
+
+    // NOTE: the position is the id of the last step that hasn't been undone, or None if every step has been undone; two
+    // positions are equal if and only if the same steps have been made to reach them
+    pub fn position(&self) -> Option<usize> {
+        self.undo_steps.last().map(|step| step.id)
+    }
+
+    // NOTE: returns the selection set of the view that made the undone step from before the step was made
+    pub fn undo(&mut self, rope: &mut Rope) -> Result<Option<SelectionSet>, RopeyError> {
+        let Some(step) = self.undo_steps.pop() else {
+            return None.ok();
+        };
+
+        for change in step.changes.iter().rev() {
+            change.revert(rope)?;
+        }
+
+        let selection_set = step.selection_set_before.clone();
+
+        self.redo_steps.push(step);
+
+        selection_set.some().ok()
+    }
+
+    // NOTE: returns the selection set of the view that made the redone step from after the step was made
+    pub fn redo(&mut self, rope: &mut Rope) -> Result<Option<SelectionSet>, RopeyError> {
+        let Some(step) = self.redo_steps.pop() else {
+            return None.ok();
+        };
+
+        for change in &step.changes {
+            change.apply(rope)?;
+        }
+
+        let selection_set = step.selection_set_after.clone();
+
+        self.undo_steps.push(step);
+
+        selection_set.some().ok()
+    }
+}
@@ -0,0 +1,283 @@
+use crate::{
+    editor::{buffer::grapheme::Grapheme, selection::region::Region},
+    utils::any::Any,
+};
+use ropey::RopeSlice;
+use std::{borrow::Cow, ops::Range};
+use unicode_segmentation::UnicodeSegmentation;
+use unicode_width::UnicodeWidthStr;
+
+pub struct Cell<'a> {
+    region: Option<Region>,
+    col: usize,
+    text: &'a str,
+}
+
+impl<'a> Cell<'a> {
+    // NOTE: the region is None for cells that don't correspond to any chars of the buffer, eg the wrap indicator
+    pub fn region(&self) -> Option<Region> {
+        self.region
+    }
+
+    pub fn col(&self) -> usize {
+        self.col
+    }
+
+    pub fn text(&self) -> &'a str {
+        self.text
+    }
+}
+
+struct SubLineCell {
+    region: Option<Region>,
+    col: usize,
+    byte_range: Range<usize>,
+}
+
+// NOTE: a sub line is a single rendered row of the buffer; it stores the rendered text of the row along with the char
+// region, display column, and byte range within that text of each cell so that all cells can share a single allocation
+#[derive(Default)]
+pub struct SubLine {
+    text: String,
+    cells: Vec<SubLineCell>,
+}
+
+impl SubLine {
+    fn push(&mut self, region: Option<Region>, col: usize, text: &str) {
+        let begin = self.text.len();
+
+        self.text.push_str(text);
+        self.cells.push(SubLineCell {
+            region,
+            col,
+            byte_range: begin..self.text.len(),
+        });
+    }
+
+    fn len(&self) -> usize {
+        self.cells.len()
+    }
+
+    fn truncate(&mut self, num_cells: usize) {
+        if let Some(cell) = self.cells.get(num_cells) {
+            self.text.truncate(cell.byte_range.start);
+        }
+
+        self.cells.truncate(num_cells);
+    }
+
+    pub fn cells(&self) -> impl Iterator<Item = Cell<'_>> {
+        self.cells.iter().map(|cell| Cell {
+            region: cell.region,
+            col: cell.col,
+            text: &self.text[cell.byte_range.clone()],
+        })
+    }
+
+    // NOTE: returns the display column of the cell containing char_idx, if any
+    pub fn col(&self, char_idx: usize) -> Option<usize> {
+        self.cells()
+            .find(|cell| {
+                cell.region()
+                    .is_some_and(|region| (region.begin()..=region.last()).contains(&char_idx))
+            })
+            .map(|cell| cell.col())
+    }
+
+    // NOTE: returns the char index of the cell containing the given display column, saturating at the last cell
+    pub fn char_idx(&self, col: usize) -> Option<usize> {
+        let mut char_idx = None;
+
+        for cell in self.cells() {
+            let Some(region) = cell.region() else {
+                continue;
+            };
+
+            char_idx = region.begin().some();
+
+            if col < cell.col().saturating_add(cell.text().width()) {
+                break;
+            }
+        }
+
+        char_idx
+    }
+}
+
+// NOTE: an item is either a grapheme of a line or the line's line break; wrapping may only break a line before a
+// breakable item
+struct Item<'a> {
+    region: Region,
+    grapheme: Option<&'a str>,
+    breakable: bool,
+}
+
+impl<'a> Item<'a> {
+    fn display(&self, col: usize, tab_width: usize) -> Cow<'a, str> {
+        match self.grapheme {
+            Some(grapheme) => Grapheme::new(grapheme).display(col, tab_width),
+            None => Layout::SPACE.into(),
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct Layout {
+    tab_width: usize,
+    soft_wrap: bool,
+    wrap_indicator: String,
+}
+
+impl Layout {
+    const LINE_BREAK_CHARS: [char; 2] = ['\n', '\r'];
+    const SPACE: &'static str = " ";
+
+    pub fn new(tab_width: usize, soft_wrap: bool, wrap_indicator: Option<String>) -> Self {
+        Self {
+            tab_width,
+            soft_wrap,
+            wrap_indicator: wrap_indicator.unwrap_or_default(),
+        }
+    }
+
+    pub fn tab_width(&self) -> usize {
+        self.tab_width
+    }
+
+    pub fn soft_wrap(&self) -> bool {
+        self.soft_wrap
+    }
+
+    pub fn toggle_soft_wrap(&mut self) {
+        self.soft_wrap = !self.soft_wrap;
+    }
+
+    // NOTE:
+    // - the line break is laid out as a single space so that a region on it is visible; the last line, which has no
+    //   line break, gets the same trailing space for the end of the buffer
+    // - a line may only be wrapped at unicode word boundaries
+    fn items(line: &str, char_idx: usize) -> Vec<Item<'_>> {
+        let content = line.trim_end_matches(Self::LINE_BREAK_CHARS);
+        let len_line_break = line[content.len()..].chars().count();
+        let mut char_idx = char_idx;
+        let mut items = std::vec![];
+
+        for segment in content.split_word_bounds() {
+            for (grapheme_idx, grapheme) in segment.graphemes(true).enumerate() {
+                let last = char_idx.saturating_add(grapheme.chars().count().saturating_sub(1));
+                let item = Item {
+                    region: Region::ii(char_idx, last),
+                    grapheme: grapheme.some(),
+                    breakable: grapheme_idx == 0,
+                };
+
+                items.push(item);
+
+                char_idx = last.saturating_add(1);
+            }
+        }
+
+        let last = char_idx.saturating_add(len_line_break.saturating_sub(1));
+        let item = Item {
+            region: Region::ii(char_idx, last),
+            grapheme: None,
+            breakable: true,
+        };
+
+        items.push(item);
+
+        items
+    }
+
+    // NOTE: lays out the items as the display columns in [begin_col, begin_col + width); items that are only partially
+    // visible are rendered as spaces in their visible columns
+    fn clipped(&self, items: &[Item], begin_col: usize, width: usize) -> SubLine {
+        let end_col = begin_col.saturating_add(width);
+        let mut sub_line = SubLine::default();
+        let mut col: usize = 0;
+
+        for item in items {
+            if end_col <= col {
+                break;
+            }
+
+            let display = item.display(col, self.tab_width);
+            let next_col = col.saturating_add(display.width());
+            let render_col = col.saturating_sub(begin_col);
+
+            if begin_col <= col && next_col <= end_col {
+                sub_line.push(item.region.some(), render_col, &display);
+            } else if begin_col < next_col {
+                let num_visible_cols = next_col.min(end_col).saturating_sub(col.max(begin_col));
+
+                sub_line.push(item.region.some(), render_col, &Self::SPACE.repeat(num_visible_cols));
+            }
+
+            col = next_col;
+        }
+
+        sub_line
+    }
+
+    // NOTE: lays out the items as rows of at most width display columns, breaking each row before the last breakable
+    // item that fits, or before the first item that doesn't fit if there is no such item; every row after the first
+    // begins with the wrap indicator
+    fn wrapped(&self, items: &[Item], width: usize) -> Vec<SubLine> {
+        let mut sub_lines = std::vec![];
+        let mut sub_line = SubLine::default();
+        let mut col: usize = 0;
+        let mut row_begin_idx = 0;
+        let mut last_break = None;
+        let mut item_idx = 0;
+
+        while let Some(item) = items.get(item_idx) {
+            let display = item.display(col, self.tab_width);
+            let next_col = col.saturating_add(display.width());
+
+            if item.breakable && row_begin_idx < item_idx {
+                last_break = (item_idx, sub_line.len()).some();
+            }
+
+            // NOTE: a row always holds at least one item so that wrapping makes progress even if width is tiny
+            if width < next_col && row_begin_idx < item_idx {
+                if let Some((break_idx, num_cells)) = last_break.take() {
+                    sub_line.truncate(num_cells);
+                    item_idx = break_idx;
+                }
+
+                sub_lines.push(sub_line.mem_take());
+
+                if !self.wrap_indicator.is_empty() {
+                    sub_line.push(None, 0, &self.wrap_indicator);
+                }
+
+                col = self.wrap_indicator.width();
+                row_begin_idx = item_idx;
+
+                continue;
+            }
+
+            sub_line.push(item.region.some(), col, &display);
+
+            col = next_col;
+            item_idx += 1;
+        }
+
+        sub_lines.push(sub_line);
+
+        sub_lines
+    }
+
+    // NOTE: lays out the line beginning at char_idx as one sub line per rendered row; begin_col is only used when soft
+    // wrapping is disabled
+    pub fn sub_lines(&self, line: RopeSlice, char_idx: usize, begin_col: usize, width: usize) -> Vec<SubLine> {
+        let line = line.convert::<Cow<str>>();
+        let items = Self::items(&line, char_idx);
+
+        if self.soft_wrap {
+            self.wrapped(&items, width)
+        } else {
+            self.clipped(&items, begin_col, width).once().collect()
+        }
+    }
+}
@@ -1,2 +1,5 @@
 pub mod buffer;
+pub mod grapheme;
+pub mod history;
+pub mod layout;
 pub mod search;
@@ -1,82 +1,581 @@
 use crate::{editor::selection::region::Region, utils::any::Any};
-use ropey::{iter::Chars as RopeChars, Rope};
-use std::{
-    iter::{Enumerate, Peekable},
-    str::Chars as StrChars,
+use regex::{Captures, Error as RegexError, Regex, RegexBuilder};
+use ropey::{
+    iter::{Chunks, Lines},
+    Rope, RopeSlice,
 };
+use serde::{Deserialize, Serialize};
+use std::{borrow::Cow, ops::Range, vec::IntoIter};
 
-struct PossibleMatch<'q> {
-    begin: usize,
-    chars: Peekable<StrChars<'q>>,
+#[derive(Clone, Copy, Debug, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SearchOption {
+    CaseInsensitive,
+    InSelection,
+    Regex,
+    SmartCase,
+    WholeWord,
 }
 
-impl<'q> PossibleMatch<'q> {
-    fn new(begin: usize, text: &'q str) -> Self {
-        Self {
-            begin,
-            chars: text.chars().peekable(),
+// NOTE: the part of the buffer that is searched: the whole buffer, or only the matches that intersect a region of the
+// primary selection when searching and that lie entirely within one when replacing
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Scope {
+    #[default]
+    Buffer,
+    Selection,
+}
+
+#[derive(Clone, Copy, Default, PartialEq)]
+enum Case {
+    #[default]
+    Sensitive,
+    Insensitive,
+    Smart,
+}
+
+// NOTE:
+// - the query is matched literally unless regex is set
+// - case insensitive search ignores case for every query, while smart case search ignores case only for queries without
+//   any uppercase chars; enabling either disables the other
+// - whole_word only matches text with a word boundary on either side
+// - the scope isn't used by the buffer itself, which always searches all of its text, but by the view to filter the
+//   matches
+#[derive(Clone, Copy, Default)]
+pub struct SearchOptions {
+    case: Case,
+    regex: bool,
+    whole_word: bool,
+    scope: Scope,
+}
+
+impl SearchOptions {
+    fn toggle_case(&mut self, case: Case) {
+        self.case = if self.case == case { Case::Sensitive } else { case };
+    }
+
+    pub fn with_regex(mut self, regex: bool) -> Self {
+        self.regex = regex;
+
+        self
+    }
+
+    pub fn toggle(&mut self, search_option: SearchOption) {
+        match search_option {
+            SearchOption::CaseInsensitive => self.toggle_case(Case::Insensitive),
+            SearchOption::InSelection => {
+                self.scope = match self.scope {
+                    Scope::Buffer => Scope::Selection,
+                    Scope::Selection => Scope::Buffer,
+                };
+            }
+            SearchOption::Regex => self.regex = !self.regex,
+            SearchOption::SmartCase => self.toggle_case(Case::Smart),
+            SearchOption::WholeWord => self.whole_word = !self.whole_word,
+        }
+    }
+
+    // NOTE: returns the names of the enabled options, eg for the search prompt
+    pub fn names(self) -> Vec<&'static str> {
+        let options = [
+            (self.case == Case::Insensitive, "case_insensitive"),
+            (self.scope == Scope::Selection, "in_selection"),
+            (self.regex, "regex"),
+            (self.case == Case::Smart, "smart_case"),
+            (self.whole_word, "whole_word"),
+        ];
+
+        options
+            .into_iter()
+            .filter_map(|(enabled, name)| enabled.then_some(name))
+            .collect()
+    }
+
+    pub fn scope(self) -> Scope {
+        self.scope
+    }
+
+    fn ignores_case(self, query: &str) -> bool {
+        match self.case {
+            Case::Sensitive => false,
+            Case::Insensitive => true,
+            Case::Smart => !query.chars().any(char::is_uppercase),
+        }
+    }
+
+    // NOTE: a query that is matched literally and exactly doesn't need a regex
+    pub fn is_exact_literal(self, query: &str) -> bool {
+        !self.regex && !self.whole_word && !self.ignores_case(query)
+    }
+}
+
+// NOTE:
+// - the regex is matched against each line of the rope without its line break, borrowing the line from its chunk when
+//   it doesn't cross a chunk boundary, so a match never spans more than one line
+// - empty matches can't be represented as regions and are skipped
+pub struct RegexSearch {
+    regex: Regex,
+    expand_replacement: bool,
+}
+
+impl RegexSearch {
+    const LINE_BREAK_CHARS: [char; 2] = ['\n', '\r'];
+
+    pub fn new(query: &str, options: SearchOptions) -> Result<Self, RegexError> {
+        let pattern = if options.regex {
+            Cow::Borrowed(query)
+        } else {
+            regex::escape(query).into()
+        };
+        let pattern = if options.whole_word {
+            std::format!(r"\b(?:{pattern})\b").into()
+        } else {
+            pattern
+        };
+        let regex = RegexBuilder::new(&pattern)
+            .case_insensitive(options.ignores_case(query))
+            .build()?;
+        let regex_search = Self {
+            regex,
+            expand_replacement: options.regex,
+        };
+
+        regex_search.ok()
+    }
+
+    fn line(line: RopeSlice) -> Cow<str> {
+        match line.convert::<Cow<str>>() {
+            Cow::Borrowed(line) => line.trim_end_matches(Self::LINE_BREAK_CHARS).into(),
+            Cow::Owned(mut line) => {
+                line.truncate(line.trim_end_matches(Self::LINE_BREAK_CHARS).len());
+
+                line.into()
+            }
+        }
+    }
+
+    // NOTE: byte ranges are converted to char ranges by counting chars from the end of the previous match on the line,
+    // so each line is only counted once
+    fn region(line: &str, char_idx: usize, offsets: &mut (usize, usize), byte_begin: usize, byte_end: usize) -> Region {
+        let (byte_idx, line_char_idx) = offsets;
+        let begin = line_char_idx.saturating_add(line[*byte_idx..byte_begin].chars().count());
+        let end = begin.saturating_add(line[byte_begin..byte_end].chars().count());
+
+        *offsets = (byte_end, end);
+
+        Region::ii(
+            char_idx.saturating_add(begin),
+            char_idx.saturating_add(end).saturating_sub(1),
+        )
+    }
+
+    // NOTE: every whole line that overlaps char_range is searched so that `\b`, `^` and `$` see the text around it, and
+    // only the matches that lie entirely within char_range are kept
+    pub fn matches<'r>(&self, rope: &'r Rope, char_range: Range<usize>) -> RegexMatches<'_, 'r> {
+        let char_range = clamp(char_range, rope.len_chars());
+        let row = rope.char_to_line(char_range.start);
+
+        RegexMatches {
+            regex: &self.regex,
+            lines: rope.lines_at(row),
+            char_idx: rope.line_to_char(row),
+            char_range,
+            line_matches: std::vec![].into_iter(),
+        }
+    }
+
+    // NOTE: when the query is a regex, `$1` or `${name}` in the replacement is replaced with the text of that capture
+    // group of each match; otherwise the replacement is inserted as is
+    pub fn replacements(&self, rope: &Rope, replacement: &str) -> Vec<(Region, String)> {
+        let mut replacements = std::vec![];
+        let mut char_idx: usize = 0;
+
+        for line in rope.lines() {
+            let len_chars = line.len_chars();
+            let line = Self::line(line);
+            let mut offsets = (0, 0);
+
+            for captures in self.regex.captures_iter(&line) {
+                let Some(found) = captures.get(0).filter(|found| !found.is_empty()) else {
+                    continue;
+                };
+                let region = Self::region(&line, char_idx, &mut offsets, found.start(), found.end());
+
+                replacements.push((region, self.replacement(&captures, replacement)));
+            }
+
+            char_idx = char_idx.saturating_add(len_chars);
+        }
+
+        replacements
+    }
+
+    fn replacement(&self, captures: &Captures, replacement: &str) -> String {
+        if !self.expand_replacement {
+            return replacement.to_owned();
+        }
+
+        let mut text = String::new();
+
+        captures.expand(replacement, &mut text);
+
+        text
+    }
+}
+
+// NOTE: matches are found one line at a time, so only the lines that are iterated over are searched
+pub struct RegexMatches<'q, 'r> {
+    regex: &'q Regex,
+    lines: Lines<'r>,
+    char_idx: usize,
+    char_range: Range<usize>,
+    line_matches: IntoIter<Region>,
+}
+
+impl Iterator for RegexMatches<'_, '_> {
+    type Item = Region;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(region) = self.line_matches.next() {
+                return region.some();
+            }
+
+            if self.char_range.end <= self.char_idx {
+                return None;
+            }
+
+            let line = self.lines.next()?;
+            let len_chars = line.len_chars();
+            let line = RegexSearch::line(line);
+            let mut offsets = (0, 0);
+            let Range { start, end } = self.char_range;
+
+            self.line_matches = self
+                .regex
+                .find_iter(&line)
+                .filter(|found| !found.is_empty())
+                .map(|found| RegexSearch::region(&line, self.char_idx, &mut offsets, found.start(), found.end()))
+                .filter(|region| start <= region.begin() && region.last() < end)
+                .collect::<Vec<_>>()
+                .into_iter();
+            self.char_idx = self.char_idx.saturating_add(len_chars);
+        }
+    }
+}
+
+// NOTE: a query compiled with its options, which can be kept for as long as neither changes so that each search doesn't
+// rebuild the regex; an exact literal query is searched for without a regex
+pub enum Pattern {
+    Literal(String),
+    Regex(RegexSearch),
+}
+
+impl Pattern {
+    pub fn new(query: &str, options: SearchOptions) -> Result<Self, RegexError> {
+        if options.is_exact_literal(query) {
+            return Self::Literal(query.to_owned()).ok();
+        }
+
+        Self::Regex(RegexSearch::new(query, options)?).ok()
+    }
+
+    // NOTE: matches are found lazily, so only as much of char_range is searched as the matches that are taken need
+    pub fn matches<'r>(&self, rope: &'r Rope, char_range: Range<usize>) -> Matches<'_, 'r> {
+        match self {
+            Self::Literal(query) => Matches::Literal(SearchIter::new(rope, char_range, query).into()),
+            Self::Regex(regex_search) => Matches::Regex(regex_search.matches(rope, char_range)),
         }
     }
 }
 
+// NOTE: the literal search and the regex search yield the same matches for an exact literal query, but the literal
+// search is much faster; the literal search is boxed since its shift table makes it much larger than the regex search
+pub enum Matches<'q, 'r> {
+    Literal(Box<SearchIter<'q, 'r>>),
+    Regex(RegexMatches<'q, 'r>),
+}
+
+impl Iterator for Matches<'_, '_> {
+    type Item = Region;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self {
+            Self::Literal(search_iter) => search_iter.next(),
+            Self::Regex(regex_matches) => regex_matches.next(),
+        }
+    }
+}
+
+// NOTE: char_range will saturate at the end of the rope
+fn clamp(char_range: Range<usize>, len_chars: usize) -> Range<usize> {
+    let end = char_range.end.min(len_chars);
+
+    char_range.start.min(end)..end
+}
+
+// NOTE:
+// - literal search uses boyer-moore-horspool over the utf-8 bytes of the rope: the window is compared against the query
+//   and, on a mismatch, is shifted by the distance from the last occurrence of its last byte in the query to the end of
+//   the query, so most bytes of the rope are never looked at
+// - since utf-8 is self-synchronizing, a byte match of a valid query always begins and ends on char boundaries
+// - the chunks of the rope are appended to a window buffer one at a time, and only the bytes at which a match could
+//   still begin are carried over to the next chunk, so matches across chunk boundaries are found without copying the
+//   whole rope
+// - only the given char range of the rope is searched, so a match must lie entirely within it
+// - matches may overlap and are reported as char-index regions in order
 pub struct SearchIter<'q, 'r> {
-    possible_matches: Vec<PossibleMatch<'q>>,
-    query: &'q str,
-    rope_iter: Enumerate<RopeChars<'r>>,
+    slice: RopeSlice<'r>,
+    char_idx: usize,
+    query: &'q [u8],
+    len_chars: usize,
+    shifts: [usize; SearchIter::NUM_BYTES],
+    chunks: Chunks<'r>,
+    window: Vec<u8>,
+    window_byte_idx: usize,
+    idx: usize,
 }
 
 impl<'q, 'r> SearchIter<'q, 'r> {
-    pub fn new(rope: &'r Rope, query: &'q str) -> Self {
+    const NUM_BYTES: usize = 256;
+
+    pub fn new(rope: &'r Rope, char_range: Range<usize>, query: &'q str) -> Self {
+        let char_range = clamp(char_range, rope.len_chars());
+        let slice = rope.slice(char_range.clone());
+        let query = query.as_bytes();
+        let mut shifts = [query.len(); Self::NUM_BYTES];
+
+        for (idx, byte) in query.iter().enumerate().take(query.len().saturating_sub(1)) {
+            shifts[usize::from(*byte)] = query.len().saturating_sub(1).saturating_sub(idx);
+        }
+
         Self {
-            possible_matches: std::vec![],
+            slice,
+            char_idx: char_range.start,
             query,
-            rope_iter: rope.chars().enumerate(),
+            len_chars: std::str::from_utf8(query).map_or(0, |query| query.chars().count()),
+            shifts,
+            chunks: slice.chunks(),
+            window: std::vec![],
+            window_byte_idx: 0,
+            idx: 0,
         }
     }
+
+    // NOTE: returns the index in the window of the next match at or after self.idx
+    fn find_in_window(&mut self) -> Option<usize> {
+        let last_idx = self.query.len().checked_sub(1)?;
+
+        while let Some(window_last) = self.window.get(self.idx.saturating_add(last_idx)) {
+            let idx = self.idx;
+
+            self.idx = idx.saturating_add(self.shifts[usize::from(*window_last)]);
+
+            if &self.window[idx..=idx.saturating_add(last_idx)] == self.query {
+                self.idx = idx.saturating_add(1);
+
+                return idx.some();
+            }
+        }
+
+        None
+    }
+
+    // NOTE: drops the bytes at which no match can begin anymore and appends the next chunk; returns false once every chunk
+    // has been appended
+    fn next_chunk(&mut self) -> bool {
+        let Some(chunk) = self.chunks.next() else {
+            return false;
+        };
+        let num_dropped_bytes = self.idx.min(self.window.len());
+
+        self.window.drain(..num_dropped_bytes);
+        self.window.extend_from_slice(chunk.as_bytes());
+
+        self.window_byte_idx = self.window_byte_idx.saturating_add(num_dropped_bytes);
+        self.idx = self.idx.saturating_sub(num_dropped_bytes);
+
+        true
+    }
 }
 
 impl<'q, 'r> Iterator for SearchIter<'q, 'r> {
     type Item = Region;
 
     fn next(&mut self) -> Option<Self::Item> {
-        for (idx, rope_char) in &mut self.rope_iter {
-            // NOTE:
-            // - need to remove items from self.possible_matches as we iterate over it
-            // - easiest way to do that is to keep a pointer to current position, and call .swap_remove() whenever
-            //   an element needs to be removed or increment the pointer otherwise
-            let possible_match = PossibleMatch::new(idx, self.query);
-            let mut i = 0;
-
-            // NOTE: possible matches starting at `idx`
-            self.possible_matches.push(possible_match);
-
-            while i < self.possible_matches.len() {
-                let possible_match = &mut self.possible_matches[i];
-                let possible_match_char = possible_match.chars.next();
-                let next_possible_match_char = possible_match.chars.peek();
-                let begin = possible_match.begin;
-
-                match (possible_match_char == Some(rope_char), next_possible_match_char) {
-                    // NOTE: possible is confimed as a match
-                    (true, None) => {
-                        self.possible_matches.swap_remove(i);
-
-                        return Region::ii(begin, idx).some();
-                    }
-
-                    // NOTE: query_chars is still valid, but has not been confirmed as a match
-                    (true, _) => {
-                        i += 1;
-
-                        continue;
-                    }
-
-                    // NOTE: query_chars is no longer valid and needs to be removed
-                    (false, _) => self.possible_matches.swap_remove(i).unit(),
-                }
+        if self.query.is_empty() {
+            return None;
+        }
+
+        loop {
+            if let Some(idx) = self.find_in_window() {
+                let begin = self
+                    .slice
+                    .byte_to_char(self.window_byte_idx.saturating_add(idx))
+                    .saturating_add(self.char_idx);
+                let last = begin.saturating_add(self.len_chars).saturating_sub(1);
+
+                return Region::ii(begin, last).some();
+            }
+
+            if !self.next_chunk() {
+                return None;
             }
         }
+    }
+}
 
-        None
+#[cfg(test)]
+mod tests {
+    use crate::editor::buffer::search::{RegexSearch, SearchIter, SearchOption, SearchOptions};
+    use ropey::Rope;
+
+    const TEXT: &str = "ab\u{e9}\u{65e5}\nba\u{e9}a\u{65e5}\u{65e5}b\n";
+
+    fn search(text: &str, query: &str) -> Vec<(usize, usize)> {
+        let rope = Rope::from_str(text);
+
+        SearchIter::new(&rope, 0..rope.len_chars(), query)
+            .map(|region| (region.begin(), region.last()))
+            .collect()
+    }
+
+    fn naive_search(text: &str, query: &str) -> Vec<(usize, usize)> {
+        let chars = text.chars().collect::<Vec<_>>();
+        let query_chars = query.chars().collect::<Vec<_>>();
+
+        if query_chars.is_empty() {
+            return std::vec![];
+        }
+
+        chars
+            .windows(query_chars.len())
+            .enumerate()
+            .filter(|(_idx, window)| *window == query_chars.as_slice())
+            .map(|(idx, _window)| (idx, idx + query_chars.len() - 1))
+            .collect()
+    }
+
+    #[test]
+    fn matches_naive_search_across_chunks() {
+        let text = TEXT.repeat(500);
+        let num_chars = text.chars().count();
+
+        assert!(Rope::from_str(&text).chunks().count() > 1);
+
+        for len_chars in 1..8 {
+            for begin in [0, 3, num_chars / 2, num_chars - len_chars] {
+                let query = text.chars().skip(begin).take(len_chars).collect::<String>();
+
+                assert_eq!(search(&text, &query), naive_search(&text, &query), "query {query:?}");
+            }
+        }
+    }
+
+    #[test]
+    fn matches_query_longer_than_chunk() {
+        let text = TEXT.repeat(500);
+        let query = text.chars().skip(7).take(1500).collect::<String>();
+
+        assert_eq!(search(&text, &query), naive_search(&text, &query));
+    }
+
+    #[test]
+    fn reports_overlapping_matches() {
+        assert_eq!(search("aaaa", "aa"), [(0, 1), (1, 2), (2, 3)]);
+        assert_eq!(search("\u{e9}\u{e9}\u{e9}", "\u{e9}\u{e9}"), [(0, 1), (1, 2)]);
+    }
+
+    #[test]
+    fn reports_match_at_end() {
+        let text = std::format!("{}xyz", TEXT.repeat(500));
+        let num_chars = text.chars().count();
+
+        assert_eq!(search(&text, "xyz"), [(num_chars - 3, num_chars - 1)]);
+        assert_eq!(search("abc", "abc"), [(0, 2)]);
+    }
+
+    #[test]
+    fn searches_only_char_range() {
+        let rope = Rope::from_str("ab\u{e9}ab\u{e9}ab");
+        let matches = SearchIter::new(&rope, 1..7, "ab")
+            .map(|region| (region.begin(), region.last()))
+            .collect::<Vec<_>>();
+
+        assert_eq!(matches, [(3, 4)]);
+    }
+
+    #[test]
+    fn regex_matches_agree_with_literal_search() {
+        let text = TEXT.repeat(50);
+        let rope = Rope::from_str(&text);
+
+        for query in ["a", "b\u{e9}", "\u{65e5}b", "\u{65e5}\u{65e5}"] {
+            for char_range in [0..rope.len_chars(), 5..200] {
+                let regex_matches = RegexSearch::new(query, SearchOptions::default())
+                    .unwrap()
+                    .matches(&rope, char_range.clone())
+                    .map(|region| (region.begin(), region.last()))
+                    .collect::<Vec<_>>();
+                let literal_matches = SearchIter::new(&rope, char_range, query)
+                    .map(|region| (region.begin(), region.last()))
+                    .collect::<Vec<_>>();
+
+                assert_eq!(regex_matches, literal_matches, "query {query:?}");
+            }
+        }
+
+        // NOTE: whole word and anchored queries over a char range that begins or ends mid-line match as they do over the
+        // whole rope
+        let text = "foobar xbar bar\nbar foo\r\nfoo foobar\n".repeat(3);
+        let rope = Rope::from_str(&text);
+        let mut whole_word = SearchOptions::default();
+
+        whole_word.toggle(SearchOption::WholeWord);
+
+        let regex = SearchOptions::default().with_regex(true);
+        let queries = [
+            ("foo", whole_word),
+            ("bar", whole_word),
+            ("^bar", regex),
+            ("foo$", regex),
+            (r"\bbar", regex),
+        ];
+        let matches = |query, options, char_range| {
+            RegexSearch::new(query, options)
+                .unwrap()
+                .matches(&rope, char_range)
+                .map(|region| (region.begin(), region.last()))
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(matches("foo", whole_word, 0..3), []);
+        assert_eq!(matches("bar", whole_word, 8..16), [(12, 14)]);
+
+        for (query, options) in queries {
+            let all_matches = matches(query, options, 0..rope.len_chars());
+
+            for char_range in [1..3, 3..9, 8..16, 9..22, 20..30, 13..50, 26..rope.len_chars()] {
+                let expected_matches = all_matches
+                    .iter()
+                    .copied()
+                    .filter(|(begin, last)| char_range.start <= *begin && *last < char_range.end)
+                    .collect::<Vec<_>>();
+
+                assert_eq!(
+                    matches(query, options, char_range.clone()),
+                    expected_matches,
+                    "query {query:?} in {char_range:?}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn reports_nothing_for_empty_query_or_missing_text() {
+        assert!(search(TEXT, "").is_empty());
+        assert!(search("", "a").is_empty());
+        assert!(search("ab", "abc").is_empty());
     }
 }
@@ -12,13 +12,23 @@ pub struct Spec {
 }
 
 impl Spec {
+    fn rgb(fg: (u8, u8, u8), bg: (u8, u8, u8)) -> Self {
+        Self {
+            fg: Self::indexed(fg),
+            bg: Self::indexed(bg),
+        }
+    }
+
     // NOTE-ac8381
     fn deserialize_color<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Color, D::Error> {
         let color = Color::deserialize(deserializer)?;
         let Color::Rgb(r, g, b) = color else { return color.ok() };
-        let index = ansi_colours::ansi256_from_rgb((r, g, b));
 
-        Color::Indexed(index).ok()
+        Self::indexed((r, g, b)).ok()
+    }
+
+    fn indexed(rgb: (u8, u8, u8)) -> Color {
+        Color::Indexed(ansi_colours::ansi256_from_rgb(rgb))
     }
 }
 
@@ -34,6 +44,19 @@ pub struct Tabs {
 pub struct Buffer {
     pub selected: Spec,
     pub unselected: Spec,
+
+    #[serde(default = "Buffer::default_matched")]
+    pub matched: Spec,
+}
+
+impl Buffer {
+    // NOTE: the same colors as buffer.matched in the default config.yaml
+    const DEFAULT_MATCHED_BG: (u8, u8, u8) = (0xeb, 0xcb, 0x8b);
+    const DEFAULT_MATCHED_FG: (u8, u8, u8) = (0x2b, 0x30, 0x3b);
+
+    fn default_matched() -> Spec {
+        Spec::rgb(Self::DEFAULT_MATCHED_FG, Self::DEFAULT_MATCHED_BG)
+    }
 }
 
 #[derive(Deserialize)]
@@ -1,21 +1,266 @@
-use serde::Deserialize;
+use crate::{
+    editor::buffer::search::{Scope, SearchOption},
+    utils::any::Any,
+};
+use ftg_macros::Registry;
+use itertools::Itertools;
+use serde::{Deserialize, Serialize};
+use serde_yaml::Value as SerdeYamlValue;
 
-#[derive(Clone, Debug, Deserialize)]
+pub struct ArgSpec {
+    pub name: &'static str,
+    pub ty: &'static str,
+}
+
+// NOTE: the spec of each command is derived from its variant: the name is the snake_case name it's given in the config,
+// the description is its doc comment, the args are its fields, and the modes are those its key bindings are in when
+// they don't declare any
+pub struct CommandSpec {
+    pub name: &'static str,
+    pub description: &'static str,
+    pub args: &'static [ArgSpec],
+    pub modes: &'static [&'static str],
+}
+
+#[derive(Clone, Debug, Deserialize, Registry, Serialize)]
 #[serde(rename_all = "snake_case", tag = "command", content = "args")]
 pub enum Command {
+    /// Delete the grapheme before each region
+    Backspace,
+
+    /// Close the search prompt, the palette, or the help
+    #[modes("search", "palette", "help")]
     Close,
+
+    /// Run the composite command with the given name
+    Composite { name: String },
+
+    /// Copy the text of each region into the register
+    Copy { register: String },
+
+    /// Copy the text of each region into the register and delete it
+    Cut { register: String },
+
+    /// Delete the grapheme at each region
+    Delete,
+
+    /// Delete the text of each region
+    DeleteSelection,
+
+    /// Extend each region backward by a grapheme
+    ExtendBackward,
+
+    /// Extend each region down by a line
+    ExtendDown,
+
+    /// Extend each region forward by a grapheme
+    ExtendForward,
+
+    /// Extend each region up by a line
+    ExtendUp,
+
+    /// Open the help listing every key binding in the given mode
+    Help { mode: String },
+
+    /// Insert a newline at each region
+    InsertNewline,
+
+    /// Move each region backward by a grapheme
     MoveBackward,
+
+    /// Move to the end of the buffer
+    MoveBufferEnd,
+
+    /// Move to the start of the buffer
+    MoveBufferStart,
+
+    /// Move each region down by a line
     MoveDown,
+
+    /// Move each region to the first non-blank char of its line
+    MoveFirstNonBlank,
+
+    /// Move each region forward by a grapheme
     MoveForward,
+
+    /// Move each region to the end of its line
+    MoveLineEnd,
+
+    /// Move each region to the start of its line
+    MoveLineStart,
+
+    /// Move each region to the next paragraph
+    MoveNextParagraph,
+
+    /// Move each region to the start of the next word
+    MoveNextWordStart,
+
+    /// Move each region and the view down by a page
+    MovePageDown,
+
+    /// Move each region and the view up by a page
+    MovePageUp,
+
+    /// Move each region to the previous paragraph
+    MovePreviousParagraph,
+
+    /// Move each region to the start of the previous word
+    MovePreviousWordStart,
+
+    /// Move each region up by a line
     MoveUp,
+
+    /// Move each region to the end of the word
+    MoveWordEnd,
+
+    /// Move a single cursor to the next match of the last search
+    NextMatch,
+
+    /// Switch to the next view
     NextView,
+
+    /// Open the command palette in the given mode
+    Palette { mode: String },
+
+    /// Move a single cursor to the previous match of the last search
+    PreviousMatch,
+
+    /// Switch to the previous view
     PreviousView,
+
+    /// Paste the texts of the register at each region
+    Paste { register: String },
+
+    /// Close the window
     Quit,
+
+    /// Redo the last undone change
+    Redo,
+
+    /// Replace each match of the query in the scope; if regex, `$1` or `${name}` in the replacement is the text of
+    /// that capture group, and since `$1x` names the group `1x`, `${1}x` must be used instead
+    Replace {
+        query: String,
+        regex: bool,
+        replacement: String,
+        scope: Scope,
+    },
+
+    /// Replay the macro in the register count times
+    ReplayMacro { register: String, count: usize },
+
+    /// Save the buffer to its file
     Save,
+
+    /// Scroll the view down by count rows
     ScrollDown { count: usize },
+
+    /// Scroll the view left by count columns
     ScrollLeft { count: usize },
+
+    /// Scroll the view right by count columns
     ScrollRight { count: usize },
+
+    /// Scroll the view up by count rows
     ScrollUp { count: usize },
-    Search,
+
+    /// Open the search prompt in the given mode
+    Search { mode: String },
+
+    /// Switch the view to the given mode
+    SetMode { mode: String },
+
+    /// Start recording a macro into the register
+    StartRecording { register: String },
+
+    /// Stop recording the macro
+    StopRecording,
+
+    /// Submit the search prompt or the palette
+    #[modes("search", "palette")]
     Submit,
+
+    /// Toggle one of the search options
+    ToggleSearchOption { option: SearchOption },
+
+    /// Toggle soft wrapping of long lines
+    ToggleSoftWrap,
+
+    /// Undo the last change
+    Undo,
+}
+
+impl Command {
+    pub const ARGS: &'static str = "args";
+    pub const TAG: &'static str = "command";
+
+    // NOTE: returns the args formatted like a yaml flow mapping as in the config, eg `{ count: 5 }`, or an empty string
+    // if the command has no args; serde_yaml only serializes block mappings, so instead each arg value is serialized as
+    // json, which quotes strings that contain flow indicators like `}` and is also valid yaml
+    pub fn args_str(&self) -> String {
+        let Ok(SerdeYamlValue::Mapping(command)) = serde_yaml::to_value(self) else {
+            return String::new();
+        };
+        let Some(SerdeYamlValue::Mapping(args)) = command.get(Self::ARGS) else {
+            return String::new();
+        };
+        let args_str = args
+            .iter()
+            .filter_map(|(name, value)| {
+                let name = name.as_str()?;
+                let value = serde_json::to_string(value).ok()?;
+
+                std::format!("{name}: {value}").some()
+            })
+            .join(", ");
+
+        std::format!("{{ {args_str} }}")
+    }
+
+    // NOTE: returns the mode the command switches the view or window to, if any
+    pub fn mode(&self) -> Option<&str> {
+        match self {
+            Self::Help { mode } | Self::Palette { mode } | Self::Search { mode } | Self::SetMode { mode } => {
+                mode.as_str().some()
+            }
+            _ => None,
+        }
+    }
+
+    // NOTE: the view isn't scrolled to the head after a command that scrolls it
+    pub fn is_scroll(&self) -> bool {
+        matches!(
+            self,
+            Self::ScrollDown { .. } | Self::ScrollLeft { .. } | Self::ScrollRight { .. } | Self::ScrollUp { .. }
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        editor::{buffer::search::Scope, command::Command},
+        utils::any::Any,
+    };
+
+    #[test]
+    fn args_str_is_a_yaml_flow_mapping() {
+        let replace = Command::Replace {
+            query: "a b".to_owned(),
+            regex: true,
+            replacement: "${1}x".to_owned(),
+            scope: Scope::Selection,
+        };
+
+        std::assert_eq!(Command::Backspace.args_str(), "");
+        std::assert_eq!(Command::ScrollDown { count: 5 }.args_str(), "{ count: 5 }");
+        std::assert!(replace
+            .args_str()
+            .deserialize_from_yaml::<serde_yaml::Mapping>()
+            .is_ok());
+        std::assert_eq!(
+            replace.args_str(),
+            "{ query: \"a b\", regex: true, replacement: \"${1}x\", scope: \"selection\" }"
+        );
+    }
 }
@@ -0,0 +1,33 @@
+use crate::editor::command::Command;
+use serde::Deserialize;
+
+// NOTE: a composite command runs its commands in order; it can only be run in the given modes, or in any mode if none
+// are given
+#[derive(Clone, Deserialize)]
+pub struct Composite {
+    name: String,
+    commands: Vec<Command>,
+
+    #[serde(default)]
+    modes: Option<Vec<String>>,
+}
+
+impl Composite {
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn commands(&self) -> &[Command] {
+        &self.commands
+    }
+
+    pub fn declared_modes(&self) -> &[String] {
+        self.modes.as_deref().unwrap_or_default()
+    }
+
+    pub fn runs_in(&self, mode: &str) -> bool {
+        self.modes
+            .as_ref()
+            .is_none_or(|modes| modes.iter().any(|composite_mode| composite_mode == mode))
+    }
+}
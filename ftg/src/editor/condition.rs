@@ -0,0 +1,365 @@
+use crate::{
+    editor::{selection::selection::Selection, view::header::Header, window::project::Project},
+    utils::{any::Any, path::Path},
+};
+use itertools::Itertools;
+use std::{
+    fmt::{Display, Formatter, Result as FmtResult},
+    str::FromStr,
+};
+
+// NOTE: the editor state a condition is evaluated against
+pub struct Environment<'a> {
+    pub header: &'a Header,
+    pub project: &'a Project,
+    pub selection: &'a Selection,
+    pub dirty: bool,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Token<'a> {
+    And,
+    Equal,
+    LeftParen,
+    Not,
+    NotEqual,
+    Or,
+    RightParen,
+    Word(&'a str),
+}
+
+impl<'a> Token<'a> {
+    fn is_word_char(chr: char) -> bool {
+        chr.is_alphanumeric() || chr == '_' || chr == '-' || chr == '.'
+    }
+
+    // NOTE: a word is either a run of word chars or any text between double quotes
+    fn tokenize(expr: &'a str) -> Result<Vec<Self>, String> {
+        let mut tokens = std::vec![];
+        let mut rest = expr.trim_start();
+
+        while let Some(chr) = rest.chars().next() {
+            let (token, len) = match chr {
+                '(' => (Self::LeftParen, 1),
+                ')' => (Self::RightParen, 1),
+                '&' if rest.starts_with("&&") => (Self::And, 2),
+                '|' if rest.starts_with("||") => (Self::Or, 2),
+                '=' if rest.starts_with("==") => (Self::Equal, 2),
+                '!' if rest.starts_with("!=") => (Self::NotEqual, 2),
+                '!' => (Self::Not, 1),
+                '"' => {
+                    let Some(len) = rest[1..].find('"') else {
+                        return std::format!("unterminated string in when expression {expr:?}").err();
+                    };
+
+                    (Self::Word(&rest[1..=len]), len.saturating_add(2))
+                }
+                _ if Self::is_word_char(chr) => {
+                    let len = rest.find(|chr| !Self::is_word_char(chr)).unwrap_or(rest.len());
+
+                    (Self::Word(&rest[..len]), len)
+                }
+                _ => return std::format!("unexpected char {chr:?} in when expression {expr:?}").err(),
+            };
+
+            tokens.push(token);
+
+            rest = rest[len..].trim_start();
+        }
+
+        tokens.ok()
+    }
+}
+
+// NOTE: a recursive descent parser for the grammar
+// or    := and ('||' and)*
+// and   := unary ('&&' unary)*
+// unary := '!' unary | '(' or ')' | atom
+// atom  := 'selection' | 'dirty' | ('extension' | 'project') ('==' | '!=') <word>
+struct Parser<'a> {
+    expr: &'a str,
+    tokens: Vec<Token<'a>>,
+    token_idx: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn peek(&self) -> Option<Token<'a>> {
+        self.tokens.get(self.token_idx).copied()
+    }
+
+    fn next(&mut self) -> Option<Token<'a>> {
+        let token = self.peek();
+
+        self.token_idx = self.token_idx.saturating_add(1);
+
+        token
+    }
+
+    fn invalid<T>(&self) -> Result<T, String> {
+        std::format!("invalid when expression {:?}", self.expr).err()
+    }
+
+    fn or(&mut self) -> Result<Condition, String> {
+        let mut conditions = std::vec![self.and()?];
+
+        while self.peek() == Some(Token::Or) {
+            self.next();
+            conditions.push(self.and()?);
+        }
+
+        if conditions.len() == 1 {
+            conditions.remove(0).ok()
+        } else {
+            Condition::Or(conditions).ok()
+        }
+    }
+
+    fn and(&mut self) -> Result<Condition, String> {
+        let mut conditions = std::vec![self.unary()?];
+
+        while self.peek() == Some(Token::And) {
+            self.next();
+            conditions.push(self.unary()?);
+        }
+
+        if conditions.len() == 1 {
+            conditions.remove(0).ok()
+        } else {
+            Condition::And(conditions).ok()
+        }
+    }
+
+    fn unary(&mut self) -> Result<Condition, String> {
+        match self.next() {
+            Some(Token::Not) => Condition::Not(self.unary()?.into()).ok(),
+            Some(Token::LeftParen) => {
+                let condition = self.or()?;
+                let Some(Token::RightParen) = self.next() else {
+                    return self.invalid();
+                };
+
+                condition.ok()
+            }
+            Some(Token::Word(name)) => self.atom(name),
+            _ => self.invalid(),
+        }
+    }
+
+    fn atom(&mut self, name: &str) -> Result<Condition, String> {
+        let make_condition = match name {
+            "selection" => return Condition::Selection.ok(),
+            "dirty" => return Condition::Dirty.ok(),
+            "extension" => Condition::Extension,
+            "project" => Condition::Project,
+            _ => return std::format!("unknown name {name:?} in when expression {:?}", self.expr).err(),
+        };
+        let negated = match self.next() {
+            Some(Token::Equal) => false,
+            Some(Token::NotEqual) => true,
+            _ => return self.invalid(),
+        };
+        let Some(Token::Word(value)) = self.next() else {
+            return self.invalid();
+        };
+        let condition = make_condition(value.to_owned());
+
+        if negated {
+            Condition::Not(condition.into()).ok()
+        } else {
+            condition.ok()
+        }
+    }
+}
+
+// NOTE: a condition is parsed from a when expression like `extension == rs && !selection`, where
+// - `extension == <word>` holds if the path of the view has the given file extension
+// - `project == <word>` holds if the window's project has a directory with the given name
+// - `selection` holds if some region of the primary selection spans more than a single char
+// - `dirty` holds if the buffer has changes that haven't been saved
+#[derive(Clone, PartialEq)]
+pub enum Condition {
+    And(Vec<Condition>),
+    Dirty,
+    Extension(String),
+    Not(Box<Condition>),
+    Or(Vec<Condition>),
+    Project(String),
+    Selection,
+}
+
+impl Condition {
+    pub fn holds(&self, environment: &Environment) -> bool {
+        match self {
+            Self::And(conditions) => conditions.iter().all(|condition| condition.holds(environment)),
+            Self::Dirty => environment.dirty,
+            Self::Extension(extension) => environment
+                .header
+                .path()
+                .and_then(Path::extension)
+                .is_some_and(|path_extension| path_extension == extension),
+            Self::Not(condition) => !condition.holds(environment),
+            Self::Or(conditions) => conditions.iter().any(|condition| condition.holds(environment)),
+            Self::Project(name) => environment.project.names().any(|project_name| project_name == name),
+            Self::Selection => environment.selection.iter().any(|region| 1 < region.len()),
+        }
+    }
+}
+
+// NOTE: the inverse of from_str(); only the operands of && that are themselves || expressions and the operands of ! that
+// aren't atoms need parentheses
+impl Display for Condition {
+    fn fmt(&self, formatter: &mut Formatter) -> FmtResult {
+        match self {
+            Self::And(conditions) => {
+                for (idx, condition) in conditions.iter().enumerate() {
+                    if idx != 0 {
+                        formatter.write_str(" && ")?;
+                    }
+
+                    if let Self::Or(_conditions) = condition {
+                        std::write!(formatter, "({condition})")?;
+                    } else {
+                        std::write!(formatter, "{condition}")?;
+                    }
+                }
+
+                ().ok()
+            }
+            Self::Dirty => formatter.write_str("dirty"),
+            Self::Extension(extension) => std::write!(formatter, "extension == {}", Word(extension)),
+            Self::Not(condition) => match condition.as_ref() {
+                Self::Extension(extension) => std::write!(formatter, "extension != {}", Word(extension)),
+                Self::Project(name) => std::write!(formatter, "project != {}", Word(name)),
+                Self::And(_conditions) | Self::Or(_conditions) => std::write!(formatter, "!({condition})"),
+                _ => std::write!(formatter, "!{condition}"),
+            },
+            Self::Or(conditions) => std::write!(formatter, "{}", conditions.iter().join(" || ")),
+            Self::Project(name) => std::write!(formatter, "project == {}", Word(name)),
+            Self::Selection => formatter.write_str("selection"),
+        }
+    }
+}
+
+// NOTE: a word is quoted unless it's a run of word chars
+struct Word<'a>(&'a str);
+
+impl Display for Word<'_> {
+    fn fmt(&self, formatter: &mut Formatter) -> FmtResult {
+        if !self.0.is_empty() && self.0.chars().all(Token::is_word_char) {
+            formatter.write_str(self.0)
+        } else {
+            std::write!(formatter, "\"{}\"", self.0)
+        }
+    }
+}
+
+impl FromStr for Condition {
+    type Err = String;
+
+    fn from_str(expr: &str) -> Result<Self, Self::Err> {
+        let tokens = Token::tokenize(expr)?;
+        let mut parser = Parser {
+            expr,
+            tokens,
+            token_idx: 0,
+        };
+        let condition = parser.or()?;
+
+        if parser.peek().is_some() {
+            return parser.invalid();
+        }
+
+        condition.ok()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::editor::condition::Condition;
+
+    fn parse(expr: &str) -> Condition {
+        match expr.parse::<Condition>() {
+            Ok(condition) => condition,
+            Err(error) => std::panic!("{error}"),
+        }
+    }
+
+    #[test]
+    fn display_round_trips_through_from_str() {
+        let exprs = [
+            "selection",
+            "dirty",
+            "extension == rs",
+            "extension != rs",
+            "project == \"my project\"",
+            "project != \"\"",
+            "!selection",
+            "!(selection && dirty)",
+            "!(selection || dirty)",
+            "!!dirty",
+            "selection && dirty && extension == md",
+            "selection || dirty || project == ftg",
+            "(selection || dirty) && extension == rs",
+            "selection || dirty && extension == rs",
+            "!(extension == rs || dirty && !selection)",
+        ];
+
+        for expr in exprs {
+            let condition = parse(expr);
+            let displayed = condition.to_string();
+
+            std::assert_eq!(displayed, expr);
+            std::assert!(parse(&displayed) == condition, "{expr}");
+        }
+    }
+
+    #[test]
+    fn display_normalizes_whitespace_and_parentheses() {
+        let exprs = [
+            ("  selection&&dirty ", "selection && dirty"),
+            ("(selection)", "selection"),
+            (
+                "((selection && dirty) && extension==rs)",
+                "selection && dirty && extension == rs",
+            ),
+            ("!(extension == rs)", "extension != rs"),
+            ("extension == \"rs\"", "extension == rs"),
+            ("selection || (dirty || selection)", "selection || dirty || selection"),
+            ("selection || (dirty && !selection)", "selection || dirty && !selection"),
+        ];
+
+        // NOTE: nested && and || expressions are flattened when parsed again, so only the displayed expressions are
+        // compared
+        for (expr, displayed) in exprs {
+            std::assert_eq!(parse(expr).to_string(), displayed);
+            std::assert_eq!(parse(displayed).to_string(), displayed);
+        }
+    }
+
+    #[test]
+    fn invalid_exprs_are_rejected() {
+        let exprs = [
+            "",
+            "selection &&",
+            "&& selection",
+            "selection dirty",
+            "(selection",
+            "selection)",
+            "!",
+            "extension",
+            "extension ==",
+            "extension == (",
+            "project rs",
+            "unknown",
+            "selection & dirty",
+            "selection | dirty",
+            "extension = rs",
+            "project == \"unterminated",
+            "selection $ dirty",
+        ];
+
+        for expr in exprs {
+            std::assert!(expr.parse::<Condition>().is_err(), "{expr:?}");
+        }
+    }
+}
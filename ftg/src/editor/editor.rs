@@ -1,12 +1,21 @@
 use crate::{
     config::Config,
     editor::{
-        buffer::buffer::Buffer,
+        buffer::{buffer::Buffer, layout::Layout},
         color_scheme::ColorScheme,
         command::Command,
-        keymap::{Context, Keymap},
-        view::view::View,
+        composite::Composite,
+        keymap::Keymap,
+        macros::Macros,
+        mode::Mode,
+        registers::Registers,
+        search_history::SearchHistory,
+        view::{scroll_margins::ScrollMargins, view::View},
         window::{
+            count::Count,
+            help::Help,
+            overlay::Overlay,
+            palette::Palette,
             project::Project,
             window::{Window, WindowArgs},
         },
@@ -15,7 +24,7 @@ use crate::{
     utils::{any::Any, container::Container, path::Path},
 };
 use crossterm::event::{Event, KeyCode, KeyEvent, KeyModifiers, MouseEvent, MouseEventKind};
-use std::io::Error as IoError;
+use std::{collections::HashMap, io::Error as IoError, time::Duration};
 use ulid::Ulid;
 
 macro_rules! key_pattern {
@@ -42,22 +51,58 @@ pub struct Editor {
     buffers: Container<Buffer>,
     windows: Container<Window>,
     keymap: Keymap,
+    modes: HashMap<String, Mode>,
+    initial_mode: String,
+    registers: Registers,
+    search_history: SearchHistory,
+    layout: Layout,
+    scroll_margins: ScrollMargins,
+    key_sequence_timeout: Duration,
+    count_key: Option<Event>,
+    macros: Macros,
+    composites: HashMap<String, Composite>,
+    nesting_depth: usize,
 }
 
 impl Editor {
     const BUFFERS_CONTAINER_NAME: &'static str = "buffers";
+    const MAX_NESTING_DEPTH: usize = 8;
     const WINDOWS_CONTAINER_NAME: &'static str = "windows";
 
     pub fn new(config: Config) -> Self {
         let buffers = Container::new(Self::BUFFERS_CONTAINER_NAME.into());
         let windows = Container::new(Self::WINDOWS_CONTAINER_NAME.into());
-        let keymap = Keymap::new(config.keymap);
+        let initial_mode = config.initial_mode();
+        let keymap = Keymap::new(&config.keymap, &initial_mode);
+        let modes = config
+            .modes
+            .into_iter()
+            .map(|mode| (mode.name().to_owned(), mode))
+            .collect();
+        let composites = config
+            .composites
+            .into_iter()
+            .map(|composite| (composite.name().to_owned(), composite))
+            .collect();
+        let registers = Registers::default();
+        let layout = Layout::new(config.tab_width, config.soft_wrap, config.wrap_indicator);
 
         Self {
             color_scheme: config.color_scheme,
             buffers,
             windows,
             keymap,
+            modes,
+            initial_mode,
+            registers,
+            search_history: SearchHistory::default(),
+            layout,
+            scroll_margins: config.scroll_margins,
+            key_sequence_timeout: Duration::from_millis(config.key_sequence_timeout_ms),
+            count_key: config.count_key,
+            macros: config.macros,
+            composites,
+            nesting_depth: 0,
         }
     }
 
@@ -75,7 +120,13 @@ impl Editor {
 
     fn get_view(&mut self, path_opt: Option<Path>) -> Result<View, Error> {
         let buffer_id = self.get_buffer_id(path_opt.as_ref())?;
-        let view = View::new(buffer_id, path_opt)?;
+        let view = View::new(
+            buffer_id,
+            path_opt,
+            self.initial_mode.clone(),
+            self.layout.clone(),
+            self.scroll_margins,
+        )?;
 
         view.ok()
     }
@@ -126,35 +177,490 @@ impl Editor {
         tracing::info!(?event);
 
         let window = self.windows.get_mut(window_id)?;
+
+        // NOTE: events fed by replaying a macro aren't recorded as the key binding that replayed it already was
+        if self.nesting_depth == 0 {
+            window.record(&event);
+        }
+
+        let mut events = window.take_pending_events();
+
+        events.push(event);
+
+        self.feed_events(window_id, &events, true)
+    }
+
+    // NOTE: handles pending events that have waited on the next event for longer than the key sequence timeout as if
+    // no longer key binding began with them
+    pub fn flush_pending_events(&mut self, window_id: &Ulid) -> Result<bool, Error> {
+        let events = self
+            .windows
+            .get_mut(window_id)?
+            .take_stale_pending_events(self.key_sequence_timeout);
+
+        self.feed_events(window_id, &events, false)
+    }
+
+    // NOTE: resolves events against the keymap from the front; events that exactly match a key binding run its
+    // command, events that are a proper prefix of a key binding wait on the next event if wait is true, and otherwise the
+    // first event is handled by itself
+    fn feed_events(&mut self, window_id: &Ulid, events: &[Event], wait: bool) -> Result<bool, Error> {
+        let mut begin = 0;
+
+        while begin < events.len() {
+            let remaining_events = &events[begin..];
+
+            if self.accumulate_count(window_id, &remaining_events[0])? {
+                begin = begin.saturating_add(1);
+
+                continue;
+            }
+
+            let window = self.windows.get_mut(window_id)?;
+            let mode = window.active_view().mode().to_owned();
+
+            if wait && self.keymap.is_prefix(&mode, remaining_events) {
+                window.set_pending_events(remaining_events.to_vec());
+
+                break;
+            }
+
+            // NOTE: the longest prefix of the remaining events that is bound is handled as a key sequence, eg if [a, b]
+            // and [a, b, c] are bound then [a, b, d] handles [a, b] and then [d]; if no prefix is bound then the first
+            // event is handled alone
+            let environment = window.environment(&self.buffers)?;
+            let len = (2..=remaining_events.len())
+                .rev()
+                .find(|len| self.keymap.get(&mode, &remaining_events[..*len], &environment).is_ok())
+                .unwrap_or(1);
+            let end = begin.saturating_add(len);
+
+            // NOTE: events fed by replaying a macro aren't recorded, see feed()
+            if self.nesting_depth == 0 {
+                window.mark_dispatch(remaining_events);
+            }
+
+            if self.handle(window_id, &events[begin..end])? {
+                return true.ok();
+            }
+
+            begin = end;
+        }
+
+        false.ok()
+    }
+
+    // NOTE: the count key begins a prefixed count in any mode, while a digit key that isn't bound in the mode of the
+    // active view adds to the count if the count is prefixed or the mode isn't an insert mode; returns true if the event
+    // was consumed by the count
+    fn accumulate_count(&mut self, window_id: &Ulid, event: &Event) -> Result<bool, Error> {
+        let window = self.windows.get_mut(window_id)?;
+
+        if self.count_key.as_ref() == Some(event) {
+            window.count_mut().begin();
+
+            return true.ok();
+        }
+
+        let Some(digit) = Count::digit(event) else {
+            return false.ok();
+        };
+        let mode = window.active_view().mode();
+        let insert = self.modes.get(mode).is_some_and(Mode::insert);
+        let bound = self.keymap.contains(mode, std::slice::from_ref(event));
+        let count = window.count_mut();
+
+        if bound || (insert && !count.is_prefixed()) {
+            return false.ok();
+        }
+
+        count.push(digit).ok()
+    }
+
+    // NOTE: macros and composite commands may run each other, so the depth they can be nested to is limited so that a
+    // cycle eventually ends; every successful call to begin_nested() must be followed by a call to end_nested()
+    fn begin_nested(&mut self) -> Result<(), Error> {
+        if Self::MAX_NESTING_DEPTH <= self.nesting_depth {
+            return Error::NestingDepth(Self::MAX_NESTING_DEPTH).err();
+        }
+
+        self.nesting_depth = self.nesting_depth.saturating_add(1);
+
+        ().ok()
+    }
+
+    fn end_nested(&mut self) {
+        self.nesting_depth = self.nesting_depth.saturating_sub(1);
+    }
+
+    fn replay_macro(&mut self, window_id: &Ulid, register: &str, count: usize) -> Result<bool, Error> {
+        let events = self.macros.events(register);
+
+        self.begin_nested()?;
+
+        let result = self.replay_events(window_id, &events, count);
+
+        self.end_nested();
+
+        result
+    }
+
+    // NOTE: the events are fed one at a time as if they were typed, and any events still pending at the end of each
+    // replay are flushed so that each replay begins in a clean state
+    fn replay_events(&mut self, window_id: &Ulid, events: &[Event], count: usize) -> Result<bool, Error> {
+        for _replay_idx in 0..count {
+            for event in events {
+                if self.feed(window_id, event.clone())? {
+                    return true.ok();
+                }
+            }
+
+            let pending_events = self.windows.get_mut(window_id)?.take_pending_events();
+
+            if self.feed_events(window_id, &pending_events, false)? {
+                return true.ok();
+            }
+        }
+
+        false.ok()
+    }
+
+    // NOTE: the commands of the composite are run count times in order, and the first command that fails stops the
+    // composite
+    fn run_composite(&mut self, window_id: &Ulid, name: &str, count: usize) -> Result<bool, Error> {
+        let mode = self.windows.get_mut(window_id)?.active_view().mode().to_owned();
+        let Some(composite) = self.composites.get(name) else {
+            return Error::UnknownName("composite command", name.to_owned()).err();
+        };
+
+        if !composite.runs_in(&mode) {
+            return Error::CompositeMode(name.to_owned(), mode).err();
+        }
+
+        let commands = composite.commands().to_vec();
+
+        self.begin_nested()?;
+
+        let result = self.run_commands(window_id, name, &commands, count);
+
+        self.end_nested();
+
+        result
+    }
+
+    fn run_commands(
+        &mut self,
+        window_id: &Ulid,
+        name: &str,
+        commands: &[Command],
+        count: usize,
+    ) -> Result<bool, Error> {
+        for _run_idx in 0..count {
+            for (command_idx, command) in commands.iter().enumerate() {
+                match self.run(window_id, command, 1) {
+                    Ok(true) => return true.ok(),
+                    Ok(false) => {}
+                    Err(error) => return Error::CompositeStep(name.to_owned(), command_idx, error.into()).err(),
+                }
+            }
+        }
+
+        false.ok()
+    }
+
+    fn handle(&mut self, window_id: &Ulid, events: &[Event]) -> Result<bool, Error> {
+        let window = self.windows.get_mut(window_id)?;
+        let mode = window.active_view().mode().to_owned();
+        let command = self
+            .keymap
+            .get(&mode, events, &window.environment(&self.buffers)?)
+            .ok()
+            .cloned();
+        let count = window.count_mut().take();
+
+        window.active_view().set_status(None);
+
+        // NOTE: a failed command is only logged and shown as the status of the active view so that it doesn't close the
+        // window, which for a composite command says which of its commands failed
+        match command {
+            Some(command) => match self.run(window_id, &command, count) {
+                Ok(quit) => quit.ok(),
+                Err(error) => {
+                    tracing::warn!(%error, ?command);
+
+                    self.windows
+                        .get_mut(window_id)?
+                        .active_view()
+                        .set_status(error.to_string().some());
+
+                    false.ok()
+                }
+            },
+            None => self.handle_unbound(window_id, events),
+        }
+    }
+
+    fn handle_unbound(&mut self, window_id: &Ulid, events: &[Event]) -> Result<bool, Error> {
+        let window = self.windows.get_mut(window_id)?;
+
+        // NOTE: typed chars are pushed to the palette query or the help filter while either is open
+        if let (Some(palette), [key_pattern!(chr)]) = (window.palette_mut(), events) {
+            palette.push(*chr);
+
+            return false.ok();
+        }
+
+        if let (Some(help), [key_pattern!(chr)]) = (window.help_mut(), events) {
+            help.push(*chr);
+
+            return false.ok();
+        }
+
+        let buffer_area = window.buffer_area();
         let view = window.active_view();
         let buffer = self.buffers.get_mut(&view.buffer_id())?;
+        let scrolled = matches!(
+            events,
+            [Event::Mouse(MouseEvent {
+                kind: MouseEventKind::ScrollDown
+                    | MouseEventKind::ScrollLeft
+                    | MouseEventKind::ScrollRight
+                    | MouseEventKind::ScrollUp,
+                ..
+            })]
+        );
+
+        // NOTE: ignored events such as mouse moves return early so that they leave the view where it is instead of
+        // moving it back to the head
+        match events {
+            [Event::Resize(width, height)] => window.resize(*width, *height)?,
+            [mouse_pattern!(ScrollUp)] => view.scroll_up(buffer, 1, buffer_area),
+            [mouse_pattern!(ScrollDown)] => view.scroll_down(buffer, 1, buffer_area),
+            [mouse_pattern!(ScrollLeft)] => view.scroll_left(1),
+            [mouse_pattern!(ScrollRight)] => view.scroll_right(buffer, 1, buffer_area),
+            [key_pattern!(chr)] if self.modes.get(view.mode()).is_some_and(Mode::insert) => {
+                view.input_char(buffer, *chr);
+            }
+            ignored_events => {
+                return tracing::info!(view.mode = view.mode(), ?ignored_events)
+                    .with(false)
+                    .ok()
+            }
+        }
 
-        match self.keymap.get(view.context(), &[event]) {
-            (_, Ok(Command::Quit)) => return true.ok(),
-            (_, Err(&[Event::Resize(width, height)])) => window.resize(width, height)?,
-            (_, Err(&[mouse_pattern!(ScrollUp)])) => view.scroll_up(1),
-            (_, Err(&[mouse_pattern!(ScrollDown)])) => view.scroll_down(buffer, 1),
-            (_, Err(&[mouse_pattern!(ScrollLeft)])) => view.scroll_left(1),
-            (_, Err(&[mouse_pattern!(ScrollRight)])) => view.scroll_right(1),
-            (Context::Buffer, Ok(Command::MoveBackward)) => view.move_backward(),
-            (Context::Buffer, Ok(Command::MoveDown)) => view.move_down(buffer),
-            (Context::Buffer, Ok(Command::MoveForward)) => view.move_forward(),
-            (Context::Buffer, Ok(Command::MoveUp)) => view.move_up(buffer),
-            (Context::Buffer, Ok(Command::NextView)) => window.next_view(),
-            (Context::Buffer, Ok(Command::PreviousView)) => window.previous_view(),
-            (Context::Buffer, Ok(Command::Save)) => view.save(buffer).warn().unit(),
-            (Context::Buffer, Ok(Command::ScrollUp { count })) => view.scroll_up(*count),
-            (Context::Buffer, Ok(Command::ScrollDown { count })) => view.scroll_down(buffer, *count),
-            (Context::Buffer, Ok(Command::ScrollLeft { count })) => view.scroll_left(*count),
-            (Context::Buffer, Ok(Command::ScrollRight { count })) => view.scroll_right(*count),
-            (Context::Buffer, Ok(Command::Search)) => view.begin_search(),
-            (Context::Buffer, Err(&[key_pattern!(chr)])) => view.insert_char(buffer, chr),
-            (Context::Search, Ok(Command::Submit)) => view.submit_search(buffer),
-            (Context::Search, Ok(Command::Close)) => view.close_search(),
-            (Context::Search, Err(&[key_pattern!(chr)])) => view.push_search(chr),
-            (context, ignored_result) => tracing::info!(view.context = ?context, ?ignored_result),
+        if !scrolled {
+            self.scroll_to_head(window_id)?;
         }
 
         false.ok()
     }
+
+    // NOTE: while the palette is open, the commands that edit a prompt edit the palette instead of the active view;
+    // returns None if the command isn't one of those commands
+    fn run_in_palette(&mut self, window_id: &Ulid, command: &Command, count: usize) -> Result<Option<bool>, Error> {
+        let window = self.windows.get_mut(window_id)?;
+        let Some(palette) = window.palette_mut() else {
+            return None.ok();
+        };
+
+        match command {
+            Command::Backspace => palette.pop(),
+            Command::Close => window.close_overlay(),
+            Command::MoveDown => palette.select_next(),
+            Command::MoveUp => palette.select_previous(),
+            Command::Submit => {
+                if let Some(palette_command) = palette.submit() {
+                    window.close_overlay();
+
+                    return self.run(window_id, &palette_command, count)?.some().ok();
+                }
+            }
+            _ => return None.ok(),
+        }
+
+        false.some().ok()
+    }
+
+    // NOTE: while the help is open, the commands that edit a prompt edit its filter and the vertical motions scroll it;
+    // returns false if the command isn't one of those commands
+    fn run_in_help(&mut self, window_id: &Ulid, command: &Command, count: usize) -> Result<bool, Error> {
+        let window = self.windows.get_mut(window_id)?;
+        let buffer_area = window.buffer_area();
+        let Some(help) = window.help_mut() else {
+            return false.ok();
+        };
+
+        match command {
+            Command::Backspace => help.pop(),
+            Command::Close => window.close_overlay(),
+            Command::MoveDown => help.scroll_down(count, buffer_area),
+            Command::MovePageDown => {
+                help.scroll_down(usize::from(buffer_area.height).saturating_mul(count), buffer_area);
+            }
+            Command::MovePageUp => help.scroll_up(usize::from(buffer_area.height).saturating_mul(count)),
+            Command::MoveUp => help.scroll_up(count),
+            _ => return false.ok(),
+        }
+
+        true.ok()
+    }
+
+    // NOTE: while the search prompt is open, the vertical motions recall queries from the search history, and submitting
+    // the search adds its query to the history without scrolling away from the current match; returns false if the
+    // command isn't one of those commands
+    fn run_in_search(&mut self, window_id: &Ulid, command: &Command) -> Result<bool, Error> {
+        let view = self.windows.get_mut(window_id)?.active_view();
+        let buffer = self.buffers.get(&view.buffer_id())?;
+
+        if !view.search().is_active() {
+            return false.ok();
+        }
+
+        match command {
+            Command::MoveDown => view.recall_search(buffer, &self.search_history, false),
+            Command::MoveUp => view.recall_search(buffer, &self.search_history, true),
+            Command::Submit => {
+                if let Some(query) = view.submit_search(buffer) {
+                    self.search_history.push(query.to_owned());
+                }
+
+                return true.ok();
+            }
+            _ => return false.ok(),
+        }
+
+        self.scroll_to_head(window_id)?;
+
+        true.ok()
+    }
+
+    fn known_mode(modes: &HashMap<String, Mode>, mode: &str) -> Result<String, Error> {
+        if !modes.contains_key(mode) {
+            return Error::UnknownName("mode", mode.to_owned()).err();
+        }
+
+        mode.to_owned().ok()
+    }
+
+    fn run(&mut self, window_id: &Ulid, command: &Command, count: usize) -> Result<bool, Error> {
+        if let Some(quit) = self.run_in_palette(window_id, command, count)? {
+            return quit.ok();
+        }
+
+        if self.run_in_help(window_id, command, count)? || self.run_in_search(window_id, command)? {
+            return false.ok();
+        }
+
+        let window = self.windows.get_mut(window_id)?;
+        let buffer_area = window.buffer_area();
+        let view = window.active_view();
+        let buffer = self.buffers.get_mut(&view.buffer_id())?;
+        let scrolled = command.is_scroll();
+
+        match command {
+            Command::Quit => return true.ok(),
+            Command::Backspace => view.backspace(buffer, count),
+            Command::Composite { name } => return self.run_composite(window_id, name, count),
+            Command::Copy { register } => {
+                self.registers.insert(register.clone(), view.copy(buffer));
+            }
+            Command::Cut { register } => {
+                self.registers.insert(register.clone(), view.cut(buffer));
+            }
+            Command::Delete => view.delete(buffer, count),
+            Command::DeleteSelection => view.delete_selection(buffer),
+            Command::ExtendBackward => view.extend_backward(buffer, count),
+            Command::ExtendDown => view.extend_down(buffer, count, buffer_area),
+            Command::ExtendForward => view.extend_forward(buffer, count),
+            Command::ExtendUp => view.extend_up(buffer, count, buffer_area),
+            Command::Help { mode } => {
+                let help = Help::new(view.mode().to_owned(), &self.keymap);
+
+                window.open_overlay(Self::known_mode(&self.modes, mode)?, Overlay::Help(help));
+            }
+            Command::InsertNewline => view.insert_newline(buffer),
+            Command::MoveBackward => view.move_backward(buffer, count),
+            Command::MoveBufferEnd => view.move_buffer_end(buffer),
+            Command::MoveBufferStart => view.move_buffer_start(),
+            Command::MoveDown => view.move_down(buffer, count, buffer_area),
+            Command::MoveFirstNonBlank => view.move_first_non_blank(buffer),
+            Command::MoveForward => view.move_forward(buffer, count),
+            Command::MoveLineEnd => view.move_line_end(buffer),
+            Command::MoveLineStart => view.move_line_start(buffer),
+            Command::MoveNextParagraph => view.move_next_paragraph(buffer, count),
+            Command::MoveNextWordStart => view.move_next_word_start(buffer, count),
+            Command::MovePageDown => view.move_page_down(buffer, count, buffer_area),
+            Command::MovePageUp => view.move_page_up(buffer, count, buffer_area),
+            Command::MovePreviousParagraph => view.move_previous_paragraph(buffer, count),
+            Command::MovePreviousWordStart => view.move_previous_word_start(buffer, count),
+            Command::MoveUp => view.move_up(buffer, count, buffer_area),
+            Command::MoveWordEnd => view.move_word_end(buffer, count),
+            Command::NextMatch => view.move_to_match(buffer, count, true),
+            Command::NextView => window.next_view(count),
+            Command::Palette { mode } => {
+                let palette = Palette::new(view.mode().to_owned(), &self.keymap);
+
+                window.open_overlay(Self::known_mode(&self.modes, mode)?, Overlay::Palette(palette));
+            }
+            Command::Paste { register } => {
+                view.paste(buffer, &self.registers.texts(register, view.num_regions()));
+            }
+            Command::PreviousMatch => view.move_to_match(buffer, count, false),
+            Command::PreviousView => window.previous_view(count),
+            Command::Redo => view.redo(buffer)?,
+            Command::Replace {
+                query,
+                regex,
+                replacement,
+                scope,
+            } => view.replace(buffer, query, *regex, replacement, *scope)?,
+            Command::ReplayMacro {
+                register,
+                count: replay_count,
+            } => return self.replay_macro(window_id, register, replay_count.saturating_mul(count)),
+            Command::Save => view.save(buffer)?,
+            Command::ScrollUp { count: scroll_count } => {
+                view.scroll_up(buffer, scroll_count.saturating_mul(count), buffer_area);
+            }
+            Command::ScrollDown { count: scroll_count } => {
+                view.scroll_down(buffer, scroll_count.saturating_mul(count), buffer_area);
+            }
+            Command::ScrollLeft { count: scroll_count } => view.scroll_left(scroll_count.saturating_mul(count)),
+            Command::ScrollRight { count: scroll_count } => {
+                view.scroll_right(buffer, scroll_count.saturating_mul(count), buffer_area);
+            }
+            Command::Search { mode } => view.begin_search(Self::known_mode(&self.modes, mode)?),
+            Command::SetMode { mode } => view.set_mode(Self::known_mode(&self.modes, mode)?),
+            Command::StartRecording { register } => window.start_recording(register.clone()),
+            Command::StopRecording => {
+                if let Some(recording) = window.stop_recording() {
+                    self.macros.insert(recording);
+                }
+            }
+            Command::ToggleSearchOption { option } => view.toggle_search_option(buffer, *option),
+            Command::ToggleSoftWrap => view.toggle_soft_wrap(),
+            Command::Undo => view.undo(buffer)?,
+            // NOTE: submit only does anything while the search prompt or the palette is open
+            Command::Submit => {}
+            Command::Close => view.close_search(),
+        }
+
+        if !scrolled {
+            self.scroll_to_head(window_id)?;
+        }
+
+        false.ok()
+    }
+
+    // NOTE: explicit scrolling is the only way to move the head of the primary region out of the visible area
+    fn scroll_to_head(&mut self, window_id: &Ulid) -> Result<(), Error> {
+        let window = self.windows.get_mut(window_id)?;
+        let buffer_area = window.buffer_area();
+        let view = window.active_view();
+        let buffer = self.buffers.get(&view.buffer_id())?;
+
+        view.scroll_to_head(buffer, buffer_area);
+
+        ().ok()
+    }
 }
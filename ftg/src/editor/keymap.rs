@@ -1,13 +1,41 @@
-use crate::{editor::command::Command, utils::any::Any};
-use crossterm::event::{Event, KeyCode, KeyEvent, KeyModifiers};
+use crate::{
+    editor::{
+        command::Command,
+        condition::{Condition, Environment},
+    },
+    utils::any::Any,
+};
+use crossterm::event::{Event, KeyCode, KeyEvent, KeyModifiers, MediaKeyCode, ModifierKeyCode};
+use itertools::Itertools;
 use serde::{de::Error, Deserialize, Deserializer};
-use std::collections::HashMap;
+use std::{
+    collections::{HashMap, HashSet},
+    fmt::{Display, Formatter, Result as FmtResult, Write},
+};
 
-#[derive(Clone, Copy, Debug, Deserialize, Eq, Hash, PartialEq)]
-#[serde(rename_all = "snake_case")]
-pub enum Context {
-    Buffer,
-    Search,
+// NOTE: a conflict is a key binding that can never be used because a key binding declared after it in the same mode
+// and for the same keys takes precedence whenever it would apply
+pub struct Conflict {
+    location: String,
+    keys: String,
+    mode: String,
+    duplicate: bool,
+    other_location: String,
+}
+
+impl Display for Conflict {
+    fn fmt(&self, formatter: &mut Formatter) -> FmtResult {
+        let verb = if self.duplicate { "duplicated" } else { "shadowed" };
+
+        std::write!(
+            formatter,
+            "{}: key binding [{}] in mode {} is {verb} by the key binding at {}",
+            self.location,
+            self.keys,
+            self.mode,
+            self.other_location,
+        )
+    }
 }
 
 #[derive(Deserialize)]
@@ -18,68 +46,224 @@ pub struct KeyBinding {
     #[serde(flatten)]
     command: Command,
 
-    #[serde(default = "KeyBinding::default_contexts")]
-    contexts: Vec<Context>,
+    // NOTE: a key binding without modes applies to the default modes of its command, see modes()
+    #[serde(default)]
+    modes: Option<Vec<String>>,
+
+    // NOTE: contexts was replaced by modes, and is only deserialized so that Config::new() can warn that it's ignored
+    #[serde(default)]
+    contexts: Option<Vec<String>>,
+
+    #[serde(default, deserialize_with = "KeyBinding::deserialize_when")]
+    when: Option<Condition>,
+
+    // NOTE: where the key binding was declared, eg config.yaml:42; set after deserialization
+    #[serde(skip)]
+    location: String,
 }
 
 impl KeyBinding {
+    const KEY_SEPARATOR: char = '+';
     const MISSING_KEY_ERROR_MESSAGE: &'static str = "No key was provided";
     const UNKNOWN_KEY_ERROR_MESSAGE: &'static str = "Unknown key was provided";
+    const UNKNOWN_MODIFIER_ERROR_MESSAGE: &'static str = "Unknown modifier was provided";
+    const MODIFIERS: [(&'static str, KeyModifiers); 6] = [
+        ("ctrl", KeyModifiers::CONTROL),
+        ("shift", KeyModifiers::SHIFT),
+        ("alt", KeyModifiers::ALT),
+        ("super", KeyModifiers::SUPER),
+        ("hyper", KeyModifiers::HYPER),
+        ("meta", KeyModifiers::META),
+    ];
+    const KEY_CODES: [(&'static str, KeyCode); 51] = [
+        ("backspace", KeyCode::Backspace),
+        ("enter", KeyCode::Enter),
+        ("left", KeyCode::Left),
+        ("right", KeyCode::Right),
+        ("up", KeyCode::Up),
+        ("down", KeyCode::Down),
+        ("home", KeyCode::Home),
+        ("end", KeyCode::End),
+        ("pageup", KeyCode::PageUp),
+        ("pagedown", KeyCode::PageDown),
+        ("tab", KeyCode::Tab),
+        ("backtab", KeyCode::BackTab),
+        ("delete", KeyCode::Delete),
+        ("insert", KeyCode::Insert),
+        ("null", KeyCode::Null),
+        ("esc", KeyCode::Esc),
+        ("space", KeyCode::Char(' ')),
+        ("capslock", KeyCode::CapsLock),
+        ("scrolllock", KeyCode::ScrollLock),
+        ("numlock", KeyCode::NumLock),
+        ("printscreen", KeyCode::PrintScreen),
+        ("pause", KeyCode::Pause),
+        ("menu", KeyCode::Menu),
+        ("keypadbegin", KeyCode::KeypadBegin),
+        ("media_play", KeyCode::Media(MediaKeyCode::Play)),
+        ("media_pause", KeyCode::Media(MediaKeyCode::Pause)),
+        ("media_playpause", KeyCode::Media(MediaKeyCode::PlayPause)),
+        ("media_reverse", KeyCode::Media(MediaKeyCode::Reverse)),
+        ("media_stop", KeyCode::Media(MediaKeyCode::Stop)),
+        ("media_fastforward", KeyCode::Media(MediaKeyCode::FastForward)),
+        ("media_rewind", KeyCode::Media(MediaKeyCode::Rewind)),
+        ("media_tracknext", KeyCode::Media(MediaKeyCode::TrackNext)),
+        ("media_trackprevious", KeyCode::Media(MediaKeyCode::TrackPrevious)),
+        ("media_record", KeyCode::Media(MediaKeyCode::Record)),
+        ("media_lowervolume", KeyCode::Media(MediaKeyCode::LowerVolume)),
+        ("media_raisevolume", KeyCode::Media(MediaKeyCode::RaiseVolume)),
+        ("media_mutevolume", KeyCode::Media(MediaKeyCode::MuteVolume)),
+        ("left_shift", KeyCode::Modifier(ModifierKeyCode::LeftShift)),
+        ("left_ctrl", KeyCode::Modifier(ModifierKeyCode::LeftControl)),
+        ("left_alt", KeyCode::Modifier(ModifierKeyCode::LeftAlt)),
+        ("left_super", KeyCode::Modifier(ModifierKeyCode::LeftSuper)),
+        ("left_hyper", KeyCode::Modifier(ModifierKeyCode::LeftHyper)),
+        ("left_meta", KeyCode::Modifier(ModifierKeyCode::LeftMeta)),
+        ("right_shift", KeyCode::Modifier(ModifierKeyCode::RightShift)),
+        ("right_ctrl", KeyCode::Modifier(ModifierKeyCode::RightControl)),
+        ("right_alt", KeyCode::Modifier(ModifierKeyCode::RightAlt)),
+        ("right_super", KeyCode::Modifier(ModifierKeyCode::RightSuper)),
+        ("right_hyper", KeyCode::Modifier(ModifierKeyCode::RightHyper)),
+        ("right_meta", KeyCode::Modifier(ModifierKeyCode::RightMeta)),
+        ("iso_level3_shift", KeyCode::Modifier(ModifierKeyCode::IsoLevel3Shift)),
+        ("iso_level5_shift", KeyCode::Modifier(ModifierKeyCode::IsoLevel5Shift)),
+    ];
+    const FUNCTION_KEY_PREFIX: char = 'f';
 
-    // NOTE: each individual event_str must be of the form
-    // [ctrl +] [shift +] [alt +] (<special-key> | <single-character>)
-    // where <special-key> is one of the special keys listed below
-    fn deserialize_key<'a, 'de, D: Deserializer<'de>>(event_str: &'a str) -> Result<Event, D::Error> {
-        let mut modifiers = KeyModifiers::NONE;
-        let mut substrs = event_str.split('+').peekable();
-
-        if let Some(&"ctrl") = substrs.peek() {
-            modifiers.insert(KeyModifiers::CONTROL);
-            substrs.next();
-        }
-
-        if let Some(&"shift") = substrs.peek() {
-            modifiers.insert(KeyModifiers::SHIFT);
-            substrs.next();
+    fn deserialize_key_code<'de, D: Deserializer<'de>>(key_str: &str) -> Result<KeyCode, D::Error> {
+        if let Some((_name, key_code)) = Self::KEY_CODES.iter().find(|(name, _key_code)| *name == key_str) {
+            return (*key_code).ok();
         }
 
-        if let Some(&"alt") = substrs.peek() {
-            modifiers.insert(KeyModifiers::ALT);
-            substrs.next();
+        if let Some(num) = key_str
+            .strip_prefix(Self::FUNCTION_KEY_PREFIX)
+            .and_then(|num_str| num_str.parse::<u8>().ok())
+        {
+            return KeyCode::F(num).ok();
         }
 
-        let Some(substr) = substrs.next() else {
+        let mut chars = key_str.chars();
+        let Some(chr) = chars.next() else {
             return D::Error::custom(Self::MISSING_KEY_ERROR_MESSAGE).err();
         };
-        let code = match substr {
-            "backspace" => KeyCode::Backspace,
-            "enter" => KeyCode::Enter,
-            "left" => KeyCode::Left,
-            "right" => KeyCode::Right,
-            "up" => KeyCode::Up,
-            "down" => KeyCode::Down,
-            "home" => KeyCode::Home,
-            "tab" => KeyCode::Tab,
-            "delete" => KeyCode::Delete,
-            "esc" => KeyCode::Esc,
-            _ => {
-                let mut chars = substr.chars();
-                let Some(chr) = chars.next() else {
-                    return D::Error::custom(Self::MISSING_KEY_ERROR_MESSAGE).err();
-                };
-                let None = chars.next() else {
-                    return D::Error::custom(Self::UNKNOWN_KEY_ERROR_MESSAGE).err();
-                };
+        let None = chars.next() else {
+            return D::Error::custom(std::format!("{}: {key_str}", Self::UNKNOWN_KEY_ERROR_MESSAGE)).err();
+        };
 
-                KeyCode::Char(chr)
-            }
+        KeyCode::Char(chr).ok()
+    }
+
+    // NOTE:
+    // - each individual event_str must be of the form <modifier> + ... + <modifier> + (<special-key> | <single-character>)
+    //   where the modifiers may be given in any order and <special-key> is one of the special keys listed above or a
+    //   function key f1 through f24
+    // - "+" by itself or after a modifier (eg "ctrl++") is the plus key
+    // - terminals report backtab along with shift, so backtab always includes the shift modifier
+    pub fn deserialize_key<'de, D: Deserializer<'de>>(event_str: &str) -> Result<Event, D::Error> {
+        let (modifiers_str, key_str) = match event_str.strip_suffix("++") {
+            Some(modifiers_str) => (modifiers_str, "+"),
+            None if event_str == "+" => ("", event_str),
+            None => event_str.rsplit_once(Self::KEY_SEPARATOR).unwrap_or(("", event_str)),
         };
+        let mut modifiers = KeyModifiers::NONE;
+
+        for modifier_str in modifiers_str.split(Self::KEY_SEPARATOR).filter(|str| !str.is_empty()) {
+            let Some((_name, modifier)) = Self::MODIFIERS.iter().find(|(name, _modifier)| *name == modifier_str) else {
+                return D::Error::custom(std::format!("{}: {modifier_str}", Self::UNKNOWN_MODIFIER_ERROR_MESSAGE))
+                    .err();
+            };
+
+            modifiers.insert(*modifier);
+        }
+
+        let code = Self::deserialize_key_code::<D>(key_str)?;
+
+        if code == KeyCode::BackTab {
+            modifiers.insert(KeyModifiers::SHIFT);
+        }
+
         let key_event = KeyEvent::new(code, modifiers);
         let event = Event::Key(key_event);
 
         event.ok()
     }
 
+    // NOTE: the inverse of deserialize_key with the modifiers in a fixed order
+    fn key_str(event: &Event) -> String {
+        let Event::Key(key_event) = event else {
+            return std::format!("{event:?}");
+        };
+        let mut key_str = String::new();
+
+        for (name, modifier) in Self::MODIFIERS {
+            if key_event.modifiers.contains(modifier) {
+                key_str.push_str(name);
+                key_str.push(Self::KEY_SEPARATOR);
+            }
+        }
+
+        match key_event.code {
+            KeyCode::F(num) => std::write!(key_str, "{}{num}", Self::FUNCTION_KEY_PREFIX).unit(),
+            KeyCode::Char(chr) if chr != ' ' => key_str.push(chr),
+            code => {
+                if let Some((name, _key_code)) = Self::KEY_CODES.iter().find(|(_name, key_code)| *key_code == code) {
+                    key_str.push_str(name);
+                }
+            }
+        }
+
+        key_str
+    }
+
+    fn keys_str(&self) -> String {
+        self.events.iter().map(Self::key_str).join(", ")
+    }
+
+    // NOTE: a key binding that doesn't declare its modes is in the default modes of its command, or in the initial
+    // mode if its command has none
+    fn modes<'a>(&'a self, initial_mode: &'a str) -> Vec<&'a str> {
+        match &self.modes {
+            Some(modes) => modes.iter().map(String::as_str).collect(),
+            None if self.command.spec().modes.is_empty() => std::vec![initial_mode],
+            None => self.command.spec().modes.to_vec(),
+        }
+    }
+
+    pub fn command(&self) -> &Command {
+        &self.command
+    }
+
+    pub fn declared_modes(&self) -> &[String] {
+        self.modes.as_deref().unwrap_or_default()
+    }
+
+    pub fn contexts(&self) -> Option<&[String]> {
+        self.contexts.as_deref()
+    }
+
+    pub fn location(&self) -> &str {
+        &self.location
+    }
+
+    pub fn set_location(&mut self, location: String) {
+        self.location = location;
+    }
+
+    fn deserialize_when<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<Condition>, D::Error> {
+        String::deserialize(deserializer)?
+            .parse::<Condition>()
+            .map_err(D::Error::custom)?
+            .some()
+            .ok()
+    }
+
+    // NOTE: deserializes a single key like those in the keys of a key binding
+    pub fn deserialize_optional_key<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<Event>, D::Error> {
+        Self::deserialize_key::<D>(&String::deserialize(deserializer)?)?
+            .some()
+            .ok()
+    }
+
     fn deserialize_keys<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<Event>, D::Error> {
         // TODO: figure out how to deserialize to &str
         Vec::deserialize(deserializer)?
@@ -88,41 +272,164 @@ impl KeyBinding {
             .map(Self::deserialize_key::<D>)
             .collect()
     }
+}
 
-    fn default_contexts() -> Vec<Context> {
-        std::vec![Context::Buffer]
-    }
+// NOTE: an active key binding is a key binding in a single one of its modes that isn't overridden by a later key
+// binding, with its keys formatted as in the config
+pub struct ActiveKeyBinding {
+    pub mode: String,
+    pub keys: String,
+    pub command: Command,
+    pub when: Option<Condition>,
 }
 
+// NOTE: several key bindings may share the same mode and events as long as their conditions differ; the last declared
+// key binding whose condition holds takes precedence
 pub struct Keymap {
-    value: HashMap<u64, Command>,
+    value: HashMap<u64, Vec<(Option<Condition>, Command)>>,
+    prefixes: HashSet<u64>,
+    keys_strs: HashMap<u64, Vec<String>>,
+    active_key_bindings: Vec<ActiveKeyBinding>,
 }
 
 impl Keymap {
-    pub fn new(key_bindings: Vec<KeyBinding>) -> Self {
+    pub fn new(key_bindings: &[KeyBinding], initial_mode: &str) -> Self {
         let mut value = HashMap::new();
+        let mut prefixes = HashSet::new();
+        let mut keys_strs = HashMap::new();
+        let mut active_key_bindings = std::vec![];
+
+        for conflict in Self::conflicts(key_bindings, initial_mode) {
+            tracing::warn!(%conflict);
+        }
 
-        for key_binding in key_bindings {
-            for context in key_binding.contexts {
-                let key = Self::key(context, &key_binding.events);
+        for (idx, key_binding) in key_bindings.iter().enumerate() {
+            for mode in key_binding.modes(initial_mode) {
+                let key = Self::key(mode, &key_binding.events);
+                let condition = key_binding.when.clone();
 
-                value.insert(key, key_binding.command.clone());
+                value
+                    .entry(key)
+                    .or_insert_with(Vec::new)
+                    .push((condition, key_binding.command.clone()));
+
+                for len in 1..key_binding.events.len() {
+                    prefixes.insert(Self::key(mode, &key_binding.events[..len]));
+                }
+
+                keys_strs
+                    .entry((mode, key_binding.command.name()).hashcode())
+                    .or_insert_with(Vec::new)
+                    .push(key_binding.keys_str());
+
+                if Self::overriding_key_binding(key_bindings, idx, mode, initial_mode).is_none() {
+                    let active_key_binding = ActiveKeyBinding {
+                        mode: mode.to_owned(),
+                        keys: key_binding.keys_str(),
+                        command: key_binding.command.clone(),
+                        when: key_binding.when.clone(),
+                    };
+
+                    active_key_bindings.push(active_key_binding);
+                }
             }
         }
 
-        Self { value }
+        Self {
+            value,
+            prefixes,
+            keys_strs,
+            active_key_bindings,
+        }
+    }
+
+    // NOTE: a key binding conflicts with a later key binding in the same mode for the same keys if the later one has no
+    // condition, in which case it is shadowed, or the same condition, in which case it is duplicated
+    pub fn conflicts(key_bindings: &[KeyBinding], initial_mode: &str) -> Vec<Conflict> {
+        let mut conflicts = std::vec![];
+
+        for (idx, key_binding) in key_bindings.iter().enumerate() {
+            for mode in key_binding.modes(initial_mode) {
+                let Some(other_key_binding) = Self::overriding_key_binding(key_bindings, idx, mode, initial_mode)
+                else {
+                    continue;
+                };
+                let conflict = Conflict {
+                    location: key_binding.location.clone(),
+                    keys: key_binding.keys_str(),
+                    mode: mode.to_owned(),
+                    duplicate: other_key_binding.when == key_binding.when,
+                    other_location: other_key_binding.location.clone(),
+                };
+
+                conflicts.push(conflict);
+            }
+        }
+
+        conflicts
+    }
+
+    // NOTE: returns the first later key binding that takes precedence over the key binding at idx in the given mode
+    fn overriding_key_binding<'a>(
+        key_bindings: &'a [KeyBinding],
+        idx: usize,
+        mode: &str,
+        initial_mode: &str,
+    ) -> Option<&'a KeyBinding> {
+        let key_binding = &key_bindings[idx];
+
+        key_bindings[idx.saturating_add(1)..].iter().find(|other_key_binding| {
+            other_key_binding.events == key_binding.events
+                && other_key_binding.modes(initial_mode).contains(&mode)
+                && (other_key_binding.when.is_none() || other_key_binding.when == key_binding.when)
+        })
+    }
+
+    pub fn active_key_bindings(&self) -> &[ActiveKeyBinding] {
+        &self.active_key_bindings
+    }
+
+    fn key(mode: &str, events: &[Event]) -> u64 {
+        (mode, events).hashcode()
+    }
+
+    // NOTE: returns true if events is a proper prefix of the events of some key binding in the given mode
+    pub fn is_prefix(&self, mode: &str, events: &[Event]) -> bool {
+        self.prefixes.contains(&Self::key(mode, events))
+    }
+
+    // NOTE: returns true if events are the events of some key binding in the given mode or a proper prefix of them
+    pub fn contains(&self, mode: &str, events: &[Event]) -> bool {
+        let key = Self::key(mode, events);
+
+        self.value.contains_key(&key) || self.prefixes.contains(&key)
     }
 
-    fn key(context: Context, events: &[Event]) -> u64 {
-        (context, events).hashcode()
+    // NOTE: returns the keys of every key binding of the named command in the given mode in the order they're declared
+    pub fn keys_strs(&self, mode: &str, command_name: &str) -> &[String] {
+        self.keys_strs
+            .get(&(mode, command_name).hashcode())
+            .map_or(&[], Vec::as_slice)
     }
 
-    pub fn get<'a>(&'a self, context: Context, events: &'a [Event]) -> (Context, Result<&'a Command, &'a [Event]>) {
-        let key = Self::key(context, events);
+    pub fn get<'a>(
+        &'a self,
+        mode: &str,
+        events: &'a [Event],
+        environment: &Environment,
+    ) -> Result<&'a Command, &'a [Event]> {
+        let key = Self::key(mode, events);
+        let command = self.value.get(&key).and_then(|key_bindings| {
+            key_bindings
+                .iter()
+                .rev()
+                .find(|(condition, _command)| condition.as_ref().is_none_or(|condition| condition.holds(environment)))
+                .map(|(_condition, command)| command)
+        });
 
-        match self.value.get(&key) {
-            Some(command) => (context, command.ok()),
-            None => (context, events.err()),
+        match command {
+            Some(command) => command.ok(),
+            None => events.err(),
         }
     }
 }
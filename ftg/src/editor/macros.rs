@@ -0,0 +1,88 @@
+use crate::{editor::keymap::KeyBinding, utils::any::Any};
+use crossterm::event::Event;
+use serde::{Deserialize, Deserializer};
+use std::collections::HashMap;
+
+// NOTE:
+// - a recording collects the key events fed to a window until it is stopped; other events aren't recorded as replaying
+//   mouse events would depend on the layout at the time, and replaying resize events would resize the window to a stale
+//   size
+// - len_before_dispatch is the length of the recording before the events of the last key sequence dispatched while no
+//   prompt was open, which is where the macro ends when the recording is stopped; this way neither the key binding
+//   that stopped the recording nor the keys that opened the prompt it was stopped from are part of the macro
+pub struct Recording {
+    register: String,
+    events: Vec<Event>,
+    len_before_dispatch: usize,
+}
+
+impl Recording {
+    pub fn new(register: String) -> Self {
+        let events = std::vec![];
+        let len_before_dispatch = 0;
+
+        Self {
+            register,
+            events,
+            len_before_dispatch,
+        }
+    }
+
+    fn is_recorded(event: &Event) -> bool {
+        matches!(event, Event::Key(_))
+    }
+
+    pub fn push(&mut self, event: &Event) {
+        if Self::is_recorded(event) {
+            self.events.push(event.clone());
+        }
+    }
+
+    // NOTE: events are the events being dispatched followed by any events after them that were fed, all of which have
+    // already been pushed
+    pub fn mark_dispatch(&mut self, events: &[Event]) {
+        let num_events = events.iter().filter(|event| Self::is_recorded(event)).count();
+
+        self.len_before_dispatch = self.events.len().saturating_sub(num_events);
+    }
+}
+
+// NOTE: a macro is a list of events that is fed back to a window as if it was typed; macros can be declared in the
+// config with the same key syntax as the keys of a key binding, eg `macros: { a: [alt+a, down] }`
+#[derive(Default, Deserialize)]
+#[serde(transparent)]
+pub struct Macros {
+    #[serde(deserialize_with = "Macros::deserialize_values")]
+    values: HashMap<String, Vec<Event>>,
+}
+
+impl Macros {
+    pub fn insert(&mut self, recording: Recording) {
+        let Recording {
+            register,
+            mut events,
+            len_before_dispatch,
+        } = recording;
+
+        events.truncate(len_before_dispatch);
+        self.values.insert(register, events);
+    }
+
+    pub fn events(&self, register: &str) -> Vec<Event> {
+        self.values.get(register).cloned().unwrap_or_default()
+    }
+
+    fn deserialize_values<'de, D: Deserializer<'de>>(deserializer: D) -> Result<HashMap<String, Vec<Event>>, D::Error> {
+        HashMap::<String, Vec<String>>::deserialize(deserializer)?
+            .into_iter()
+            .map(|(register, keys)| {
+                let events = keys
+                    .iter()
+                    .map(|key| KeyBinding::deserialize_key::<D>(key))
+                    .collect::<Result<_, _>>()?;
+
+                (register, events).ok()
+            })
+            .collect()
+    }
+}
@@ -1,9 +1,15 @@
 pub mod buffer;
 pub mod color_scheme;
 pub mod command;
+pub mod composite;
+pub mod condition;
 pub mod editor;
 pub mod keymap;
+pub mod macros;
+pub mod mode;
+pub mod registers;
 pub mod render;
+pub mod search_history;
 pub mod selection;
 pub mod terminal;
 pub mod view;
@@ -0,0 +1,25 @@
+use serde::Deserialize;
+
+// NOTE: a mode is a named set of key bindings; printable keys that aren't bound in the active mode insert text if and
+// only if the mode is an insert mode
+#[derive(Clone, Deserialize)]
+pub struct Mode {
+    name: String,
+
+    #[serde(default)]
+    insert: bool,
+}
+
+impl Mode {
+    pub fn new(name: String, insert: bool) -> Self {
+        Self { name, insert }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn insert(&self) -> bool {
+        self.insert
+    }
+}
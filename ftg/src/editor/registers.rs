@@ -0,0 +1,30 @@
+use std::collections::HashMap;
+
+// NOTE: a register holds one text per region of the selection it was copied from so that pasting into a selection
+// with the same number of regions can give each region its own text
+#[derive(Default)]
+pub struct Registers {
+    values: HashMap<String, Vec<String>>,
+}
+
+impl Registers {
+    const SEPARATOR: &'static str = "\n";
+
+    pub fn insert(&mut self, name: String, texts: Vec<String>) {
+        self.values.insert(name, texts);
+    }
+
+    // NOTE: returns one text per region if the register holds exactly num_regions texts, and otherwise returns the
+    // register's texts joined together for every region
+    pub fn texts(&self, name: &str, num_regions: usize) -> Vec<String> {
+        let Some(texts) = self.values.get(name) else {
+            return std::vec![];
+        };
+
+        if texts.len() == num_regions {
+            texts.clone()
+        } else {
+            std::vec![texts.join(Self::SEPARATOR); num_regions]
+        }
+    }
+}
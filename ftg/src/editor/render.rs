@@ -1,17 +1,27 @@
 use crate::{
     editor::{
-        buffer::buffer::Buffer, color_scheme::ColorScheme, terminal::Terminal, view::view::View,
-        window::project::Project,
+        buffer::buffer::Buffer,
+        color_scheme::ColorScheme,
+        terminal::Terminal,
+        view::{search::Search, view::View},
+        window::{
+            help::{Help, Row},
+            overlay::Overlay,
+            palette::Palette,
+            project::Project,
+        },
     },
     error::Error,
     utils::{any::Any, container::Container},
 };
 use ratatui::{
+    layout::Rect,
     style::Stylize,
     text::{Line, Span},
     widgets::Paragraph,
 };
-use std::borrow::Cow;
+use std::{borrow::Cow, fmt::Write};
+use unicode_width::UnicodeWidthStr;
 
 pub struct Render<'a> {
     terminal: &'a mut Terminal,
@@ -19,12 +29,15 @@ pub struct Render<'a> {
     view_index: usize,
     view: &'a View,
     project: &'a Project,
+    overlay: Option<&'a Overlay>,
     buffer: &'a Buffer,
     color_scheme: &'a ColorScheme,
 }
 
 impl<'a> Render<'a> {
     const DOTS_WIDTH: u16 = 5;
+    const HEADER_HEIGHT: u16 = 2;
+    const SPACE: &'static str = " ";
     const TAB_WIDTH: u16 = 15;
 
     pub fn new(
@@ -32,6 +45,7 @@ impl<'a> Render<'a> {
         views: &'a [View],
         view_index: usize,
         project: &'a Project,
+        overlay: Option<&'a Overlay>,
         buffers: &'a Container<Buffer>,
         color_scheme: &'a ColorScheme,
     ) -> Result<Self, Error> {
@@ -43,6 +57,7 @@ impl<'a> Render<'a> {
             view_index,
             view,
             project,
+            overlay,
             buffer,
             color_scheme,
         };
@@ -50,6 +65,11 @@ impl<'a> Render<'a> {
         render.ok()
     }
 
+    // NOTE: the area below the title and tabs in which the buffer is rendered
+    pub fn buffer_area(terminal_area: Rect) -> Rect {
+        terminal_area.saturating_sub_from_top(Self::HEADER_HEIGHT)
+    }
+
     fn render_title(&mut self) {
         let view_title = self.view.header().title();
         let title = if let Some(project_title) = self.project.title() {
@@ -63,6 +83,28 @@ impl<'a> Render<'a> {
             .render_widget(title, self.terminal.area().width.row_at(0, 0));
     }
 
+    // NOTE: the mode of the active view is shown at the right end of the title row, preceded by its status if it has
+    // one
+    fn render_mode(&mut self) {
+        let mode = std::format!(" {} ", self.view.mode());
+        let width = u16::try_from(mode.width()).unwrap_or(u16::MAX);
+        let render_area_x = self.terminal.area().width.saturating_sub(width);
+        let mode = mode.paragraph().color(&self.color_scheme.title).bold();
+
+        self.terminal.render_widget(mode, width.row_at(render_area_x, 0));
+
+        let Some(status) = self.view.status() else {
+            return;
+        };
+        let status = std::format!(" {status} ");
+        let status_width = u16::try_from(status.width()).unwrap_or(u16::MAX).min(render_area_x);
+        let status_area_x = render_area_x.saturating_sub(status_width);
+        let status = status.paragraph().color(&self.color_scheme.title).italic();
+
+        self.terminal
+            .render_widget(status, status_width.row_at(status_area_x, 0));
+    }
+
     fn dots(&self, near_edge: bool) -> Paragraph<'static> {
         let dots = if near_edge { "" } else { "..." };
 
@@ -136,80 +178,67 @@ impl<'a> Render<'a> {
     }
 
     fn render_buffer(&mut self) {
-        let buffer_area = self.terminal.area().saturating_sub_from_top(2);
-        let sub_lines = self.buffer.sub_lines(self.view.position(), buffer_area);
+        let buffer_area = Self::buffer_area(self.terminal.area());
+        let sub_lines = self
+            .buffer
+            .sub_lines(self.view.position(), buffer_area, self.view.layout());
         let mut selection_regions = self.view.selection_set().primary().iter();
         let mut selection_region_opt = selection_regions.next();
+        let search_matches = self.view.visible_search_matches(self.buffer, buffer_area);
+        let mut search_matches = search_matches.iter();
+        let mut search_match_opt = search_matches.next();
         let background = Paragraph::default().color(&self.color_scheme.buffer.unselected);
 
         self.terminal.render_widget(background, buffer_area);
 
-        for (render_y, sub_line) in (2..).zip(sub_lines) {
-            // NOTE-ad63f1:
-            // - we call sub_line.graphemes() and process the graphemes iterator to avoid the O(log N) [1] cost of
-            //   having to index into the sub_line rope slice multiple times
-            // - see [2] for the source of the implementation
-            // - [1]: [https://docs.rs/ropey/latest/ropey/struct.Rope.html#method.slice]
-            // - [2]: [~/tree/projects/.scratch/python/notebooks/2024-05-07-chunks.ipynb]
-            let mut sub_line_sub_region_opt = sub_line.region();
-            let mut sub_line_graphemes = sub_line.graphemes();
+        for (render_y, sub_line) in (buffer_area.y..).zip(sub_lines) {
             let mut sub_line_spans = std::vec![];
             let mut selection_region_on_this_line = false;
 
-            loop {
-                // NOTE: if the current sub_line remainder is empty, then i'm done processing the sub_line, and i can
-                // continue onto the next sub_line
-                let Some(sub_line_sub_region) = sub_line_sub_region_opt else {
-                    break;
-                };
-
-                // NOTE: if there are no more selection regions, yield the current sub_line remainder and continue
-                // onto the next sub_line
-                let Some(selection_region) = &selection_region_opt else {
-                    sub_line_graphemes
-                        .spans(sub_line_sub_region.len())
-                        .push_all_to(&mut sub_line_spans);
+            for cell in sub_line.cells() {
+                let span = Span::raw(cell.text());
+                let Some(cell_region) = cell.region() else {
+                    sub_line_spans.push(span);
 
-                    break;
+                    continue;
                 };
 
-                let Some(intersection) = selection_region.intersect(&sub_line_sub_region) else {
-                    // NOTE: sub_line_sub_region is nonempty, so if the intersection is empty and selection_region
-                    // is to the left of sub_line_sub_region, then selection_region must end before sub_line_sub_region
-                    // begins, and i can skip to the next selection_region
-                    if selection_region.begin() < sub_line_sub_region.begin() {
-                        selection_region_opt = selection_regions.next();
+                // NOTE: cells and selection regions are both sorted, so a selection region that ends before the
+                // current cell begins also ends before every later cell begins and can be skipped
+                while let Some(selection_region) = selection_region_opt {
+                    if cell_region.begin() <= selection_region.last() {
+                        break;
+                    }
 
-                        continue;
+                    selection_region_opt = selection_regions.next();
+                }
+
+                // NOTE: search matches are sorted too and are skipped the same way
+                while let Some(search_match) = search_match_opt {
+                    if cell_region.begin() <= search_match.last() {
+                        break;
                     }
 
-                    // NOTE: otherwise, selection_region is to the right of sub_line_sub_region, and i can skip to the
-                    // next sub_line after first yielding the current sub_line remainder
-                    sub_line_graphemes
-                        .spans(sub_line_sub_region.len())
-                        .push_all_to(&mut sub_line_spans);
+                    search_match_opt = search_matches.next();
+                }
 
-                    break;
+                let selected = selection_region_opt
+                    .is_some_and(|selection_region| selection_region.intersect(&cell_region).is_some());
+                let matched =
+                    search_match_opt.is_some_and(|search_match| search_match.intersect(&cell_region).is_some());
+                let span = if matched {
+                    span.color(&self.color_scheme.buffer.matched)
+                } else {
+                    span
                 };
 
-                selection_region_on_this_line = true;
+                if selected {
+                    selection_region_on_this_line = true;
 
-                // NOTE: if the beginning of the current sub_line remainder is not included in the intersection, yield
-                // it
-                if sub_line_sub_region.begin() < intersection.begin() {
-                    let len_chars = intersection.begin().saturating_sub(sub_line_sub_region.begin());
-
-                    sub_line_graphemes.spans(len_chars).push_all_to(&mut sub_line_spans);
+                    sub_line_spans.push(span.reversed());
+                } else {
+                    sub_line_spans.push(span);
                 }
-
-                // NOTE: yield the intersection
-                sub_line_graphemes
-                    .spans(intersection.len())
-                    .map(Span::reversed)
-                    .push_all_to(&mut sub_line_spans);
-
-                // NOTE: update the current sub_line remainder so that it begins after the end of the intersection
-                sub_line_sub_region_opt = sub_line_sub_region.try_with_begin(intersection.end_exclusive());
             }
 
             let spec = if selection_region_on_this_line {
@@ -225,10 +254,126 @@ impl<'a> Render<'a> {
         }
     }
 
+    // NOTE: the search prompt is drawn over the bottom row of the buffer area as the query followed by the enabled
+    // search options and either the number of matches or that the query isn't a valid regex
+    fn render_search(&mut self) {
+        let search = self.view.search();
+
+        if !search.is_active() {
+            return;
+        }
+
+        let buffer_area = Self::buffer_area(self.terminal.area());
+        let Some(render_y) = buffer_area.bottom().checked_sub(1) else {
+            return;
+        };
+        let mut prompt = std::format!("/{}", search.query());
+        let option_names = search.options().names();
+
+        if !option_names.is_empty() {
+            std::write!(prompt, "  [{}]", option_names.join(", ")).unit();
+        }
+
+        if search.error().is_some() {
+            prompt.push_str("  (invalid regex)");
+        } else if search.num_matches() < Search::MAX_NUM_MATCHES {
+            std::write!(prompt, "  ({} matches)", search.num_matches()).unit();
+        } else {
+            std::write!(prompt, "  ({}+ matches)", Search::MAX_NUM_MATCHES).unit();
+        }
+
+        let prompt = prompt.paragraph().color(&self.color_scheme.title).bold();
+
+        self.terminal
+            .render_widget(prompt, buffer_area.width.row_at(0, render_y));
+    }
+
+    // NOTE: the palette is drawn over the top of the buffer area as its prompt followed by as many of its matches as
+    // fit, scrolled so that the selected match is visible, or by the error of the last rejected arg
+    fn render_palette(&mut self, palette: &Palette) {
+        let buffer_area = Self::buffer_area(self.terminal.area());
+        let Some(num_match_rows) = usize::from(buffer_area.height).checked_sub(1) else {
+            return;
+        };
+        let width = usize::from(buffer_area.width);
+        let prompt = std::format!("{}{}", palette.prompt(), palette.query());
+        let prompt = prompt.paragraph().color(&self.color_scheme.title).bold();
+        let num_skipped_matches = palette.selected_idx().saturating_add(1).saturating_sub(num_match_rows);
+
+        self.terminal
+            .render_widget(prompt, buffer_area.width.row_at(0, buffer_area.y));
+
+        // NOTE: no matches are listed while an arg is asked for, so the error of a rejected arg takes the first row
+        if let Some(error) = palette.error().filter(|_error| 0 < num_match_rows) {
+            let error = error.paragraph().color(&self.color_scheme.title).italic();
+
+            self.terminal
+                .render_widget(error, buffer_area.width.row_at(0, buffer_area.y.saturating_add(1)));
+        }
+
+        for ((match_idx, entry), render_y) in palette
+            .matches()
+            .enumerate()
+            .skip(num_skipped_matches)
+            .take(num_match_rows)
+            .zip(buffer_area.y.saturating_add(1)..)
+        {
+            let name = std::format!("{}  {}", entry.name(), entry.description());
+            let padding_width = width.saturating_sub(name.width()).saturating_sub(entry.keys().width());
+            let row = std::format!("{name}{}{}", Self::SPACE.repeat(padding_width), entry.keys());
+            let spec = if match_idx == palette.selected_idx() {
+                &self.color_scheme.buffer.selected
+            } else {
+                &self.color_scheme.buffer.unselected
+            };
+            let row = row.paragraph().color(spec);
+
+            self.terminal.render_widget(row, buffer_area.width.row_at(0, render_y));
+        }
+    }
+
+    // NOTE: the help is drawn over the whole buffer area as its filter followed by the visible rows; mode rows are drawn
+    // in the style of the title
+    fn render_help(&mut self, help: &Help) {
+        let buffer_area = Self::buffer_area(self.terminal.area());
+        let filter = std::format!("filter: {}", help.filter());
+        let filter = filter.paragraph().color(&self.color_scheme.title).bold();
+        let background = Paragraph::default().color(&self.color_scheme.buffer.unselected);
+
+        self.terminal.render_widget(background, buffer_area);
+        self.terminal
+            .render_widget(filter, buffer_area.width.row_at(0, buffer_area.y));
+
+        for (row, render_y) in help
+            .rows()
+            .into_iter()
+            .skip(help.y())
+            .zip(buffer_area.y.saturating_add(1)..buffer_area.bottom())
+        {
+            let row = match row {
+                Row::Mode(mode) => mode.as_str().paragraph().color(&self.color_scheme.title).bold(),
+                Row::KeyBinding(key_binding) => key_binding
+                    .as_str()
+                    .paragraph()
+                    .color(&self.color_scheme.buffer.unselected),
+            };
+
+            self.terminal.render_widget(row, buffer_area.width.row_at(0, render_y));
+        }
+    }
+
     pub fn render(&mut self) -> Result<Vec<u8>, Error> {
         self.render_title();
+        self.render_mode();
         self.render_tabs();
         self.render_buffer();
+        self.render_search();
+
+        match self.overlay {
+            Some(Overlay::Help(help)) => self.render_help(help),
+            Some(Overlay::Palette(palette)) => self.render_palette(palette),
+            None => {}
+        }
 
         self.terminal.finish()
     }
@@ -0,0 +1,32 @@
+// NOTE: the history holds the queries submitted from the search prompt of every view, oldest first and without
+// duplicates, so that resubmitting a query moves it to the end
+#[derive(Default)]
+pub struct SearchHistory {
+    queries: Vec<String>,
+}
+
+impl SearchHistory {
+    const MAX_LEN: usize = 100;
+
+    pub fn push(&mut self, query: String) {
+        if query.is_empty() {
+            return;
+        }
+
+        self.queries.retain(|other_query| other_query != &query);
+        self.queries.push(query);
+
+        if Self::MAX_LEN < self.queries.len() {
+            self.queries.remove(0);
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.queries.len()
+    }
+
+    // NOTE: idx counts back from the most recent query
+    pub fn get(&self, idx: usize) -> Option<&str> {
+        self.queries.iter().rev().nth(idx).map(String::as_str)
+    }
+}
@@ -2,23 +2,32 @@ use crate::utils::any::Any;
 use nodit::{InclusiveInterval, Interval};
 
 // NOTE: Copy impl needed for InclusiveInterval impl: [https://docs.rs/nodit/latest/nodit/interval/trait.InclusiveInterval.html]
+// NOTE: sticky_col is the display column a region should return to when moving vertically through shorter lines
 #[derive(Clone, Copy)]
 pub struct Region {
     begin: usize,
     last: usize,
     reversed: bool,
+    sticky_col: Option<usize>,
 }
 
 impl Region {
     fn new(begin: usize, last: usize, reversed: bool) -> Result<Self, Self> {
         // TODO: should i change it to [if (begin <= last) == reversed]
         if begin <= last {
-            Self { begin, last, reversed }.ok()
+            Self {
+                begin,
+                last,
+                reversed,
+                sticky_col: None,
+            }
+            .ok()
         } else {
             Self {
                 begin: last,
                 last: begin,
                 reversed,
+                sticky_col: None,
             }
             .err()
         }
@@ -36,13 +45,6 @@ impl Region {
         Self::ii(begin, begin)
     }
 
-    pub fn try_ie(begin: usize, end: usize) -> Option<Self> {
-        let last = end.saturating_sub(1);
-        let result = Self::new(begin, last, false);
-
-        result.ok()
-    }
-
     pub fn begin(&self) -> usize {
         self.begin
     }
@@ -55,6 +57,45 @@ impl Region {
         self.reversed
     }
 
+    pub fn sticky_col(&self) -> Option<usize> {
+        self.sticky_col
+    }
+
+    pub fn with_sticky_col(mut self, sticky_col: usize) -> Self {
+        self.sticky_col = sticky_col.some();
+
+        self
+    }
+
+    // NOTE: the head of a region is the end that moves when the region is extended, and the anchor is the end that stays
+    // fixed; the head is the beginning of a reversed region and the last of a non-reversed region
+    pub fn head(&self) -> usize {
+        if self.reversed() {
+            self.begin()
+        } else {
+            self.last()
+        }
+    }
+
+    pub fn anchor(&self) -> usize {
+        if self.reversed() {
+            self.last()
+        } else {
+            self.begin()
+        }
+    }
+
+    // NOTE: region is reversed iff the new head crosses to the left of the anchor
+    pub fn with_head(&self, head: usize) -> Self {
+        let anchor = self.anchor();
+
+        if head < anchor {
+            Self::new(head, anchor, true).into_inner()
+        } else {
+            Self::new(anchor, head, false).into_inner()
+        }
+    }
+
     pub fn end_exclusive(&self) -> usize {
         self.last().saturating_add(1)
     }
@@ -70,13 +111,9 @@ impl Region {
         Self::try_ii(begin, last)
     }
 
-    pub fn try_with_begin(&self, begin: usize) -> Option<Self> {
-        Self::new(begin, self.last(), self.reversed()).ok()
-    }
-
-    pub fn translate_by(&self, count: isize) -> Self {
-        let begin = self.begin().saturating_add_signed(count);
-        let last = self.last().saturating_add_signed(count);
+    // NOTE: moves the region as a whole so that it begins at begin
+    pub fn translate_to(&self, begin: usize) -> Self {
+        let last = begin.saturating_add(self.len().saturating_sub(1));
         let result = Self::new(begin, last, self.reversed());
 
         result.into_inner()
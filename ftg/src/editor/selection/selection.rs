@@ -2,7 +2,7 @@ use crate::{editor::selection::region::Region, utils::any::Any};
 use derive_more::From;
 use nodit::NoditSet;
 
-#[derive(Default, From)]
+#[derive(Clone, Default, From)]
 pub struct Selection {
     regions: NoditSet<usize, Region>,
 }
@@ -4,7 +4,7 @@ use crate::{
 };
 use derive_more::From;
 
-#[derive(From)]
+#[derive(Clone, From)]
 pub struct SelectionSet {
     selections: Vec<Selection>,
 }
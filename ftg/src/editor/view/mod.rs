@@ -1,3 +1,4 @@
 pub mod header;
+pub mod scroll_margins;
 pub mod search;
 pub mod view;
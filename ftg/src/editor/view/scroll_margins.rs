@@ -0,0 +1,12 @@
+use serde::Deserialize;
+
+// NOTE: the minimum number of rows above and below, and columns to the left and right of, the head of the primary
+// region that are kept visible when the view scrolls automatically
+#[derive(Clone, Copy, Default, Deserialize)]
+pub struct ScrollMargins {
+    #[serde(default)]
+    pub vertical: usize,
+
+    #[serde(default)]
+    pub horizontal: usize,
+}
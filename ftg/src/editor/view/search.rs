@@ -1,18 +1,176 @@
+use crate::{
+    editor::{
+        buffer::search::{Pattern, SearchOption, SearchOptions},
+        search_history::SearchHistory,
+        selection::{region::Region, selection::Selection, set::SelectionSet},
+    },
+    utils::{any::Any, position::Position},
+};
+use regex::Error as RegexError;
+
+// NOTE: the state of the view when the search prompt was opened, which is restored if the search is closed without
+// being submitted
+pub struct Origin {
+    pub mode: String,
+    pub selection_set: SelectionSet,
+    pub position: Position,
+}
+
+// NOTE:
+// - pattern is the query compiled with the options, which is only rebuilt when either changes since the matches that
+//   are highlighted are searched for on every render
+// - current_match is the match the view is scrolled to, and num_matches is the number of matches in the buffer up to
+//   MAX_NUM_MATCHES; both are updated whenever the query or options change, which only searches the buffer as far as
+//   needed to find them, while the matches that are highlighted are found when rendering within the visible rows
+// - error is set instead of pattern while the query isn't a valid regex
+// - the options, along with the last submitted query, the pattern it was compiled to, and the primary selection it was
+//   submitted from, are kept between searches
+// - history_idx is the index of the query recalled from the search history, counting back from the most recent query
 #[derive(Default)]
 pub struct Search {
     query: String,
+    pattern: Option<Pattern>,
+    origin: Option<Origin>,
+    current_match: Option<Region>,
+    num_matches: usize,
+    error: Option<RegexError>,
+    options: SearchOptions,
+    last_query: String,
+    last_options: SearchOptions,
+    last_pattern: Option<Pattern>,
+    last_selection: Selection,
+    history_idx: Option<usize>,
 }
 
 impl Search {
+    pub const MAX_NUM_MATCHES: usize = 10_000;
+
     pub fn query(&self) -> &str {
         &self.query
     }
 
+    pub fn last_query(&self) -> &str {
+        &self.last_query
+    }
+
+    pub fn last_options(&self) -> SearchOptions {
+        self.last_options
+    }
+
+    pub fn last_pattern(&self) -> Option<&Pattern> {
+        self.last_pattern.as_ref()
+    }
+
+    pub fn last_selection(&self) -> &Selection {
+        &self.last_selection
+    }
+
+    pub fn pattern(&self) -> Option<&Pattern> {
+        self.pattern.as_ref()
+    }
+
+    pub fn current_match(&self) -> Option<Region> {
+        self.current_match
+    }
+
+    pub fn num_matches(&self) -> usize {
+        self.num_matches
+    }
+
+    pub fn error(&self) -> Option<&RegexError> {
+        self.error.as_ref()
+    }
+
+    pub fn set_matches(&mut self, current_match: Option<Region>, num_matches: usize) {
+        self.current_match = current_match;
+        self.num_matches = num_matches;
+    }
+
+    fn compile(&mut self) {
+        (self.pattern, self.error) = match Pattern::new(&self.query, self.options) {
+            Ok(pattern) => (pattern.some(), None),
+            Err(error) => (None, error.some()),
+        };
+    }
+
+    pub fn options(&self) -> SearchOptions {
+        self.options
+    }
+
+    pub fn toggle(&mut self, search_option: SearchOption) {
+        self.options.toggle(search_option);
+
+        self.compile();
+    }
+
     pub fn push(&mut self, chr: char) {
         self.query.push(chr);
+        self.history_idx = None;
+
+        self.compile();
+    }
+
+    pub fn pop(&mut self) {
+        self.query.pop();
+        self.history_idx = None;
+
+        self.compile();
+    }
+
+    // NOTE: replaces the query with the next older or newer query in the history; going newer than the most recent query
+    // clears the query
+    pub fn recall(&mut self, search_history: &SearchHistory, older: bool) {
+        let history_idx = match (self.history_idx, older) {
+            (None, true) => 0,
+            (Some(history_idx), true) => history_idx.saturating_add(1),
+            (None | Some(0), false) => {
+                self.query.clear();
+                self.history_idx = None;
+
+                return self.compile();
+            }
+            (Some(history_idx), false) => history_idx.saturating_sub(1),
+        };
+        let history_idx = history_idx.min(search_history.len().saturating_sub(1));
+
+        if let Some(query) = search_history.get(history_idx) {
+            query.clone_into(&mut self.query);
+
+            self.history_idx = history_idx.some();
+
+            self.compile();
+        }
+    }
+
+    // NOTE: the search prompt is open while there is an origin to return to once it's closed
+    pub fn is_active(&self) -> bool {
+        self.origin.is_some()
+    }
+
+    pub fn begin(&mut self, origin: Origin) {
+        self.origin = origin.some();
     }
 
-    pub fn clear(&mut self) {
+    pub fn end(&mut self) -> Option<Origin> {
         self.query.clear();
+        self.pattern = None;
+        self.current_match = None;
+        self.num_matches = 0;
+        self.error = None;
+        self.history_idx = None;
+
+        self.origin.take()
+    }
+
+    // NOTE: ends the search, keeping the query as the last submitted query
+    pub fn submit(&mut self) -> Option<Origin> {
+        let origin = self.origin.as_ref()?;
+
+        self.last_selection = origin.selection_set.primary().clone();
+        self.last_query.clone_from(&self.query);
+        self.last_options = self.options;
+        self.last_pattern = self.pattern.take();
+
+        self.end()
     }
 }
@@ -1,14 +1,24 @@
 use crate::{
     editor::{
-        buffer::buffer::Buffer,
-        keymap::Context,
+        buffer::{
+            buffer::{Buffer, LineCharIndices},
+            layout::Layout,
+            search::{Pattern, Scope, SearchOption, SearchOptions},
+        },
+        search_history::SearchHistory,
         selection::{region::Region, selection::Selection, set::SelectionSet},
-        view::{header::Header, search::Search},
+        view::{
+            header::Header,
+            scroll_margins::ScrollMargins,
+            search::{Origin, Search},
+        },
     },
     error::Error,
     utils::{any::Any, container::Identifiable, path::Path, position::Position},
 };
-use std::io::Error as IoError;
+use ratatui::layout::Rect;
+use ropey::Error as RopeyError;
+use std::{io::Error as IoError, ops::Range};
 use ulid::Ulid;
 
 pub struct View {
@@ -17,26 +27,38 @@ pub struct View {
     position: Position,
     header: Header,
     selection_set: SelectionSet,
-    context: Context,
+    mode: String,
     search: Search,
+    layout: Layout,
+    scroll_margins: ScrollMargins,
+    status: Option<String>,
 }
 
 impl View {
-    pub fn new(buffer_id: Ulid, filepath: Option<Path>) -> Result<Self, Error> {
+    pub fn new(
+        buffer_id: Ulid,
+        filepath: Option<Path>,
+        mode: String,
+        layout: Layout,
+        scroll_margins: ScrollMargins,
+    ) -> Result<Self, Error> {
         let id = Ulid::new();
         let header = Header::new(filepath);
         let position = Position::zero();
         let selection_set = Region::unit(0).into();
-        let context = Context::Buffer;
         let search = Search::default();
+        let status = None;
         let view = Self {
             id,
             buffer_id,
             position,
             header,
             selection_set,
-            context,
+            mode,
             search,
+            layout,
+            scroll_margins,
+            status,
         };
 
         view.ok()
@@ -62,93 +84,693 @@ impl View {
         &self.selection_set
     }
 
-    pub fn context(&self) -> Context {
-        self.context
+    pub fn search(&self) -> &Search {
+        &self.search
     }
 
-    fn translate_by(&mut self, count: isize) {
+    pub fn mode(&self) -> &str {
+        &self.mode
+    }
+
+    // NOTE: the status is the error of the last command that failed in the view, which is shown until the next key
+    // sequence is handled
+    pub fn status(&self) -> Option<&str> {
+        self.status.as_deref()
+    }
+
+    pub fn set_status(&mut self, status: Option<String>) {
+        self.status = status;
+    }
+
+    pub fn set_mode(&mut self, mode: String) {
+        self.mode = mode;
+    }
+
+    pub fn layout(&self) -> &Layout {
+        &self.layout
+    }
+
+    // NOTE: the horizontal scroll position is meaningless when soft wrapping and the sub line scroll position is
+    // meaningless otherwise, so both are reset
+    pub fn toggle_soft_wrap(&mut self) {
+        self.layout.toggle_soft_wrap();
+
+        self.position.x = 0;
+        self.position.sub_y = 0;
+    }
+
+    // NOTE: returns the char index count graphemes away from char_idx
+    fn nth_grapheme(buffer: &Buffer, char_idx: usize, forward: bool, count: usize) -> usize {
+        (0..count).fold(char_idx, |char_idx, _idx| {
+            if forward {
+                buffer.next_grapheme(char_idx)
+            } else {
+                buffer.previous_grapheme(char_idx)
+            }
+        })
+    }
+
+    fn translate_by_grapheme(&mut self, buffer: &Buffer, forward: bool, count: usize) {
         let selection = self.selection_set.primary_mut();
 
-        *selection = selection.iter().map(|region| region.translate_by(count)).collect();
+        *selection = selection
+            .iter()
+            .map(|region| {
+                let begin = Self::nth_grapheme(buffer, region.begin(), forward, count);
+
+                region.translate_to(begin)
+            })
+            .collect();
     }
 
-    pub fn translate_by_line(&mut self, buffer: &Buffer, count: isize) {
+    // NOTE:
+    // - returns the char indices of the row count rows away from the row of char_idx, where rows are sub lines when soft
+    //   wrapping and lines otherwise, along with the display column that was moved to
+    // - the display column moved to is sticky_col if given and the display column of char_idx otherwise
+    fn line_char_indices(
+        layout: &Layout,
+        buffer: &Buffer,
+        char_idx: usize,
+        sticky_col: Option<usize>,
+        count: isize,
+        area: Rect,
+    ) -> (LineCharIndices, usize) {
+        if !layout.soft_wrap() {
+            let (row, col) = buffer.row_col(char_idx, layout.tab_width());
+            let col = sticky_col.unwrap_or(col);
+            let row = row.saturating_add_signed(count);
+
+            return (buffer.char_idx(row, col, layout.tab_width()), col);
+        }
+
+        let width = area.width.into();
+        let (row, sub_row, col) = buffer.sub_row_col(char_idx, layout, width);
+        let col = sticky_col.unwrap_or(col);
+        let (row, sub_row) = buffer.add_sub_rows((row, sub_row), count, layout, width);
+        let query = buffer.sub_row_char_idx(row, sub_row, col, layout, width);
+        let line_char_indices = LineCharIndices {
+            begin: buffer.line_begin(query),
+            last: buffer.line_end(query),
+            query,
+        };
+
+        (line_char_indices, col)
+    }
+
+    // NOTE: the display column each region moves to is its sticky column if it has one and the display column of its
+    // beginning otherwise; the moved region keeps that column as its sticky column
+    pub fn translate_by_line(&mut self, buffer: &Buffer, count: isize, area: Rect) {
         let selection = self.selection_set.primary_mut();
 
         *selection = selection
             .iter()
             .map(|region| {
-                let (row, col) = buffer.row_col(region.begin());
-                let row = row.saturating_add_signed(count);
-                let line_char_indices = buffer.char_idx(row, col);
+                let (line_char_indices, col) =
+                    Self::line_char_indices(&self.layout, buffer, region.begin(), region.sticky_col(), count, area);
                 let begin = line_char_indices.query;
                 let last = begin
                     .saturating_add(region.len().saturating_sub(1))
                     .min(line_char_indices.last);
 
-                Region::ii(begin, last)
+                Region::ii(begin, last).with_sticky_col(col)
             })
             .collect();
     }
 
-    pub fn move_backward(&mut self) {
-        self.translate_by(-1);
+    fn extend_by_grapheme(&mut self, buffer: &Buffer, forward: bool, count: usize) {
+        let selection = self.selection_set.primary_mut();
+
+        *selection = selection
+            .iter()
+            .map(|region| {
+                let head = Self::nth_grapheme(buffer, region.head(), forward, count);
+
+                region.with_head(head)
+            })
+            .collect();
+    }
+
+    fn extend_by_line(&mut self, buffer: &Buffer, count: isize, area: Rect) {
+        let selection = self.selection_set.primary_mut();
+
+        *selection = selection
+            .iter()
+            .map(|region| {
+                let (line_char_indices, col) =
+                    Self::line_char_indices(&self.layout, buffer, region.head(), region.sticky_col(), count, area);
+
+                region.with_head(line_char_indices.query).with_sticky_col(col)
+            })
+            .collect();
+    }
+
+    pub fn extend_backward(&mut self, buffer: &Buffer, count: usize) {
+        self.extend_by_grapheme(buffer, false, count);
+    }
+
+    pub fn extend_down(&mut self, buffer: &Buffer, count: usize, area: Rect) {
+        self.extend_by_line(buffer, count.try_into().unwrap_or(isize::MAX), area);
     }
 
-    pub fn move_down(&mut self, buffer: &Buffer) {
-        self.translate_by_line(buffer, 1);
+    pub fn extend_forward(&mut self, buffer: &Buffer, count: usize) {
+        self.extend_by_grapheme(buffer, true, count);
     }
 
-    pub fn move_forward(&mut self) {
-        self.translate_by(1);
+    pub fn extend_up(&mut self, buffer: &Buffer, count: usize, area: Rect) {
+        self.extend_by_line(buffer, -count.try_into().unwrap_or(isize::MAX), area);
     }
 
-    pub fn move_up(&mut self, buffer: &Buffer) {
-        self.translate_by_line(buffer, -1);
+    // NOTE: replaces each region in the primary selection with the unit region at the char index returned by `char_idx`
+    // given the region's head
+    fn move_to<F: Fn(usize) -> usize>(&mut self, char_idx: F) {
+        let selection = self.selection_set.primary_mut();
+
+        *selection = selection
+            .iter()
+            .map(|region| Region::unit(char_idx(region.head())))
+            .collect();
     }
 
-    pub fn scroll_down(&mut self, buffer: &Buffer, count: usize) {
-        let max_y = buffer.len_lines().saturating_sub(2);
+    // NOTE: like move_to() but `next_char_idx` is applied to each head count times
+    fn move_by<F: Fn(usize) -> usize>(&mut self, count: usize, next_char_idx: F) {
+        self.move_to(|head| (0..count).fold(head, |char_idx, _idx| next_char_idx(char_idx)));
+    }
+
+    pub fn move_buffer_end(&mut self, buffer: &Buffer) {
+        self.move_to(|_head| buffer.len_chars());
+    }
+
+    pub fn move_buffer_start(&mut self) {
+        self.move_to(|_head| 0);
+    }
+
+    pub fn move_first_non_blank(&mut self, buffer: &Buffer) {
+        self.move_to(|head| buffer.first_non_blank(head));
+    }
 
-        self.position.y = self.position.y.saturating_add(count).min(max_y);
+    pub fn move_line_end(&mut self, buffer: &Buffer) {
+        self.move_to(|head| buffer.line_end(head));
     }
 
-    pub fn scroll_up(&mut self, count: usize) {
-        self.position.y = self.position.y.saturating_sub(count);
+    pub fn move_line_start(&mut self, buffer: &Buffer) {
+        self.move_to(|head| buffer.line_begin(head));
     }
 
+    pub fn move_next_paragraph(&mut self, buffer: &Buffer, count: usize) {
+        self.move_by(count, |char_idx| buffer.next_paragraph(char_idx));
+    }
+
+    pub fn move_next_word_start(&mut self, buffer: &Buffer, count: usize) {
+        self.move_by(count, |char_idx| buffer.next_word_start(char_idx));
+    }
+
+    pub fn move_previous_paragraph(&mut self, buffer: &Buffer, count: usize) {
+        self.move_by(count, |char_idx| buffer.previous_paragraph(char_idx));
+    }
+
+    pub fn move_previous_word_start(&mut self, buffer: &Buffer, count: usize) {
+        self.move_by(count, |char_idx| buffer.previous_word_start(char_idx));
+    }
+
+    pub fn move_word_end(&mut self, buffer: &Buffer, count: usize) {
+        self.move_by(count, |char_idx| buffer.word_end(char_idx));
+    }
+
+    // NOTE: paging moves both the regions and the visible area by the height of the visible area once per page
+    pub fn move_page_down(&mut self, buffer: &Buffer, count: usize, area: Rect) {
+        let num_rows = usize::from(area.height).saturating_mul(count);
+
+        self.translate_by_line(buffer, num_rows.try_into().unwrap_or(isize::MAX), area);
+        self.scroll_down(buffer, num_rows, area);
+    }
+
+    pub fn move_page_up(&mut self, buffer: &Buffer, count: usize, area: Rect) {
+        let num_rows = usize::from(area.height).saturating_mul(count);
+
+        self.translate_by_line(buffer, -num_rows.try_into().unwrap_or(isize::MAX), area);
+        self.scroll_up(buffer, num_rows, area);
+    }
+
+    pub fn move_backward(&mut self, buffer: &Buffer, count: usize) {
+        self.translate_by_grapheme(buffer, false, count);
+    }
+
+    pub fn move_down(&mut self, buffer: &Buffer, count: usize, area: Rect) {
+        self.translate_by_line(buffer, count.try_into().unwrap_or(isize::MAX), area);
+    }
+
+    pub fn move_forward(&mut self, buffer: &Buffer, count: usize) {
+        self.translate_by_grapheme(buffer, true, count);
+    }
+
+    pub fn move_up(&mut self, buffer: &Buffer, count: usize, area: Rect) {
+        self.translate_by_line(buffer, -count.try_into().unwrap_or(isize::MAX), area);
+    }
+
+    // NOTE: when soft wrapping, scrolling moves by sub lines rather than by lines
+    fn scroll_sub_rows(&mut self, buffer: &Buffer, count: isize, area: Rect) {
+        let (y, sub_y) = buffer.add_sub_rows(
+            (self.position.y, self.position.sub_y),
+            count,
+            &self.layout,
+            area.width.into(),
+        );
+
+        self.position.y = y;
+        self.position.sub_y = sub_y;
+    }
+
+    pub fn scroll_down(&mut self, buffer: &Buffer, count: usize, area: Rect) {
+        if self.layout.soft_wrap() {
+            self.scroll_sub_rows(buffer, count.try_into().unwrap_or(isize::MAX), area);
+        } else {
+            let max_y = buffer.len_lines().saturating_sub(2);
+
+            self.position.y = self.position.y.saturating_add(count).min(max_y);
+        }
+    }
+
+    pub fn scroll_up(&mut self, buffer: &Buffer, count: usize, area: Rect) {
+        if self.layout.soft_wrap() {
+            self.scroll_sub_rows(buffer, -count.try_into().unwrap_or(isize::MAX), area);
+        } else {
+            self.position.y = self.position.y.saturating_sub(count);
+        }
+    }
+
+    // NOTE: scrolling horizontally is a no-op when soft wrapping as every line fits in the width of the view
     pub fn scroll_left(&mut self, count: usize) {
+        if self.layout.soft_wrap() {
+            return;
+        }
+
         self.position.x = self.position.x.saturating_sub(count);
     }
 
-    // TODO: need to find max value (requires getting length of each line that's being rendered)
-    pub fn scroll_right(&mut self, count: usize) {
-        self.position.x = self.position.x.saturating_add(count);
+    // NOTE: the largest horizontal scroll position at which the end of the widest visible line is still visible
+    fn max_x(&self, buffer: &Buffer, area: Rect) -> usize {
+        let rows = self.position.y..self.position.y.saturating_add(area.height.into());
+
+        buffer
+            .max_width(rows, self.layout.tab_width())
+            .saturating_sub(area.width.into())
+    }
+
+    pub fn scroll_right(&mut self, buffer: &Buffer, count: usize, area: Rect) {
+        if self.layout.soft_wrap() {
+            return;
+        }
+
+        self.position.x = self.position.x.saturating_add(count).min(self.max_x(buffer, area));
+    }
+
+    // NOTE:
+    // - scrolls just enough that the head of the first region of the primary selection is at least the scroll margins
+    //   away from the edges of the visible area
+    // - each margin is shrunk to at most half of the visible area so that the head always fits between the margins
+    // - while the search prompt is open, the view scrolls to the current match rather than the head
+    // - nothing is visible in an empty area, so there is nothing to scroll to
+    pub fn scroll_to_head(&mut self, buffer: &Buffer, area: Rect) {
+        if area.is_empty() {
+            return;
+        }
+
+        let head_opt = match self.search.current_match() {
+            Some(current_match) => current_match.begin().some(),
+            None => self.selection_set.primary().iter().next().map(Region::head),
+        };
+        let Some(head) = head_opt else {
+            return;
+        };
+        let width: usize = area.width.into();
+        let height: usize = area.height.into();
+        let vertical_margin = self.scroll_margins.vertical.min(height.saturating_sub(1) / 2);
+        let bottom_margin = height.saturating_sub(1).saturating_sub(vertical_margin);
+        let head_position = if self.layout.soft_wrap() {
+            let (row, sub_row, _col) = buffer.sub_row_col(head, &self.layout, width);
+
+            (row, sub_row)
+        } else {
+            let (row, _col) = buffer.row_col(head, self.layout.tab_width());
+
+            (row, 0)
+        };
+        let min_position = buffer.add_sub_rows(
+            head_position,
+            -bottom_margin.try_into().unwrap_or(isize::MAX),
+            &self.layout,
+            width,
+        );
+        let max_position = buffer.add_sub_rows(
+            head_position,
+            -vertical_margin.try_into().unwrap_or(isize::MAX),
+            &self.layout,
+            width,
+        );
+        let (y, sub_y) = (self.position.y, self.position.sub_y).clamp(min_position, max_position);
+
+        self.position.y = y;
+        self.position.sub_y = sub_y;
+
+        if self.layout.soft_wrap() {
+            return;
+        }
+
+        let horizontal_margin = self.scroll_margins.horizontal.min(width.saturating_sub(1) / 2);
+        let (_row, col) = buffer.row_col(head, self.layout.tab_width());
+        let min_x = col
+            .saturating_add(horizontal_margin)
+            .saturating_add(1)
+            .saturating_sub(width);
+        let max_x = col.saturating_sub(horizontal_margin);
+
+        self.position.x = self.position.x.clamp(min_x, max_x).min(self.max_x(buffer, area));
+    }
+
+    // NOTE: the search prompt is edited in the given mode and the view returns to its current mode once the prompt is
+    // closed
+    pub fn begin_search(&mut self, mode: String) {
+        if self.search.is_active() {
+            return;
+        }
+
+        let origin = Origin {
+            mode: self.mode.replace_with(mode),
+            selection_set: self.selection_set.clone(),
+            position: self.position.clone(),
+        };
+
+        self.search.begin(origin);
     }
 
-    pub fn begin_search(&mut self) {
-        self.context = Context::Search;
+    // NOTE: returns a predicate that is true for the regions that relate to a region of the selection as given by
+    // related(selection_region, region); it must be called with sorted regions, since a selection region that ends
+    // before a region begins can't relate to any later region either and is skipped
+    fn relates<'a>(
+        selection: &'a Selection,
+        related: impl Fn(&Region, &Region) -> bool + 'a,
+    ) -> impl FnMut(&Region) -> bool + 'a {
+        let mut selection_regions = selection.iter().peekable();
+
+        move |region| {
+            while let Some(selection_region) = selection_regions.peek() {
+                if region.begin() <= selection_region.last() {
+                    break;
+                }
+
+                selection_regions.next();
+            }
+
+            selection_regions
+                .peek()
+                .is_some_and(|selection_region| related(selection_region, region))
+        }
     }
 
-    pub fn push_search(&mut self, chr: char) {
+    // NOTE: the selection scope of the search prompt keeps the matches that intersect a region of the selection
+    fn intersects(selection: &Selection) -> impl FnMut(&Region) -> bool + '_ {
+        Self::relates(selection, |selection_region, region| {
+            selection_region.intersect(region).is_some()
+        })
+    }
+
+    // NOTE: the selection scope of a replace only keeps the matches that lie entirely within a region of the selection,
+    // so that no text outside of the selection is replaced
+    fn lies_within(selection: &Selection) -> impl FnMut(&Region) -> bool + '_ {
+        Self::relates(selection, |selection_region, region| {
+            selection_region.begin() <= region.begin() && region.last() <= selection_region.last()
+        })
+    }
+
+    // NOTE:
+    // - returns the matches of the query within char_range lazily, so that only as much of the buffer is searched as the
+    //   matches that are taken need
+    // - with a selection, only the matches that intersect one of its regions are kept, and only the rows around its
+    //   regions are searched
+    fn search_matches<'a>(
+        buffer: &'a Buffer,
+        query: &str,
+        pattern: &'a Pattern,
+        char_range: Range<usize>,
+        selection: Option<&'a Selection>,
+    ) -> impl Iterator<Item = Region> + 'a {
+        let char_range = match selection.map(|selection| (selection.iter().next(), selection.iter().next_back())) {
+            Some((Some(first_region), Some(last_region))) => {
+                let len_chars = query.chars().count();
+                let first_row = buffer.row(first_region.begin().saturating_sub(len_chars));
+                let last_row = buffer.row(last_region.last().saturating_add(len_chars));
+                let scope_char_range = buffer.rows_char_range(first_row..last_row.saturating_add(1));
+
+                char_range.start.max(scope_char_range.start)..char_range.end.min(scope_char_range.end)
+            }
+            _ => char_range,
+        };
+        let mut intersects = selection.map(Self::intersects);
+
+        buffer
+            .search(pattern, char_range)
+            .filter(move |region| intersects.as_mut().is_none_or(|intersects| intersects(region)))
+    }
+
+    // NOTE: with the selection scope, the search prompt only matches within the primary selection
+    fn scope_selection(&self) -> Option<&Selection> {
+        (self.search.options().scope() == Scope::Selection).then(|| self.selection_set.primary())
+    }
+
+    // NOTE: the current match is the first match that begins at or after the head of the first region of the primary
+    // selection, wrapping around to the first match in the buffer
+    fn find_search_matches(&self, buffer: &Buffer) -> (Option<Region>, usize) {
+        let Some(pattern) = self.search.pattern() else {
+            return (None, 0);
+        };
+        let (query, selection) = (self.search.query(), self.scope_selection());
+        let len_chars = buffer.len_chars();
+        let head = self.selection_set.primary().iter().next().map_or(0, Region::head);
+        let num_matches = Self::search_matches(buffer, query, pattern, 0..len_chars, selection)
+            .take(Search::MAX_NUM_MATCHES)
+            .count();
+        let current_match = Self::search_matches(buffer, query, pattern, head..len_chars, selection)
+            .next()
+            .or_else(|| Self::search_matches(buffer, query, pattern, 0..len_chars, selection).next());
+
+        (current_match, num_matches)
+    }
+
+    fn update_search_matches(&mut self, buffer: &Buffer) {
+        let (current_match, num_matches) = self.find_search_matches(buffer);
+
+        self.search.set_matches(current_match, num_matches);
+    }
+
+    // NOTE: returns the matches of the search query that can be seen in the area; the visible rows are widened by the
+    // length of the query so that matches that only partly lie within them are found too
+    pub fn visible_search_matches(&self, buffer: &Buffer, area: Rect) -> Vec<Region> {
+        let (true, Some(pattern)) = (self.search.is_active(), self.search.pattern()) else {
+            return std::vec![];
+        };
+        let query = self.search.query();
+        let len_chars = query.chars().count();
+        let rows = self.position.y..self.position.y.saturating_add(area.height.into());
+        let char_range = buffer.rows_char_range(rows);
+        let char_range = char_range.start.saturating_sub(len_chars)..char_range.end.saturating_add(len_chars);
+
+        Self::search_matches(buffer, query, pattern, char_range, self.scope_selection()).collect()
+    }
+
+    pub fn toggle_search_option(&mut self, buffer: &Buffer, search_option: SearchOption) {
+        self.search.toggle(search_option);
+
+        if self.search.is_active() {
+            self.update_search_matches(buffer);
+        }
+    }
+
+    // NOTE:
+    // - replaces every match of the query, which is matched case sensitively and as a regex only if regex is set, so
+    //   the replace doesn't depend on the options of the search prompt; with the selection scope, only matches that lie
+    //   entirely within a region of the primary selection are replaced
+    // - the replaced texts become the selection, unless nothing was replaced; an empty replacement leaves a cursor where
+    //   its match was
+    pub fn replace(
+        &mut self,
+        buffer: &mut Buffer,
+        query: &str,
+        regex: bool,
+        replacement: &str,
+        scope: Scope,
+    ) -> Result<(), Error> {
+        let selection_set_before = self.selection_set.clone();
+        let options = SearchOptions::default().with_regex(regex);
+        let replacements = buffer.replacements(query, replacement, options)?;
+        let replacements = match scope {
+            Scope::Buffer => replacements,
+            Scope::Selection => {
+                let mut lies_within = Self::lies_within(self.selection_set.primary());
+
+                replacements
+                    .into_iter()
+                    .filter(|(region, _text)| lies_within(region))
+                    .collect()
+            }
+        };
+        let mut new_selection = Selection::default();
+        let mut len_chars_inserted = 0;
+        let mut len_chars_removed = 0;
+
+        if replacements.is_empty() {
+            return ().ok();
+        }
+
+        for (region, text) in replacements {
+            let replace_idx = region
+                .begin()
+                .saturating_add(len_chars_inserted)
+                .saturating_sub(len_chars_removed);
+            let len_chars = text.chars().count();
+
+            let len_removed = buffer.remove(replace_idx..replace_idx.saturating_add(region.len()))?;
+
+            buffer.insert(replace_idx, &text)?;
+
+            let new_region = match len_chars.checked_sub(1) {
+                Some(last_offset) => Region::ii(replace_idx, replace_idx.saturating_add(last_offset)),
+                None => Region::unit(replace_idx.min(buffer.len_chars())),
+            };
+
+            new_selection.insert(new_region);
+
+            len_chars_inserted = len_chars_inserted.saturating_add(len_chars);
+            len_chars_removed = len_chars_removed.saturating_add(len_removed);
+        }
+
+        self.selection_set.primary_mut().replace_with(new_selection);
+        buffer.commit(selection_set_before, self.selection_set.clone());
+
+        ().ok()
+    }
+
+    pub fn push_search(&mut self, buffer: &Buffer, chr: char) {
         self.search.push(chr);
+        self.update_search_matches(buffer);
+    }
+
+    pub fn pop_search(&mut self, buffer: &Buffer) {
+        self.search.pop();
+        self.update_search_matches(buffer);
+    }
+
+    pub fn recall_search(&mut self, buffer: &Buffer, search_history: &SearchHistory, older: bool) {
+        self.search.recall(search_history, older);
+        self.update_search_matches(buffer);
+    }
+
+    // NOTE: the matches replace the selection unless there are none, in which case the selection is left as it was
+    // when the search began; only the first MAX_NUM_MATCHES matches are selected so that submitting a query that
+    // matches nearly every char of a large buffer stays responsive; the view stays scrolled to the current match;
+    // returns the submitted query
+    pub fn submit_search(&mut self, buffer: &Buffer) -> Option<&str> {
+        if let Some(pattern) = self.search.pattern() {
+            let matches = Self::search_matches(
+                buffer,
+                self.search.query(),
+                pattern,
+                0..buffer.len_chars(),
+                self.scope_selection(),
+            )
+            .take(Search::MAX_NUM_MATCHES)
+            .collect::<Vec<_>>();
+
+            if !matches.is_empty() {
+                self.selection_set = matches.into_iter().collect();
+            }
+        }
 
-        // TODO: remove
-        tracing::info!(view.search.query = ?self.search.query());
+        let origin = self.search.submit()?;
+
+        self.mode = origin.mode;
+
+        self.search.last_query().some()
+    }
+
+    // NOTE: returns the last match within char_range; whole rows are searched backward from its end over a number of
+    // rows that doubles whenever no match is found, so only about twice as many rows are searched as lie after the match
+    fn last_search_match(
+        buffer: &Buffer,
+        query: &str,
+        pattern: &Pattern,
+        char_range: Range<usize>,
+        selection: Option<&Selection>,
+    ) -> Option<Region> {
+        let end_row = buffer.row(char_range.end);
+        let mut num_rows: usize = 0;
+
+        loop {
+            let begin_row = end_row.saturating_sub(num_rows);
+            let begin = buffer.rows_char_range(begin_row..begin_row).start.max(char_range.start);
+            let last_match = Self::search_matches(buffer, query, pattern, begin..char_range.end, selection).last();
+
+            if last_match.is_some() || begin <= char_range.start {
+                return last_match;
+            }
+
+            num_rows = num_rows.saturating_mul(2).max(1);
+        }
     }
 
-    pub fn submit_search(&mut self, buffer: &Buffer) {
-        self.selection_set = buffer.search(self.search.query()).collect();
+    // NOTE: moves a single cursor to the beginning of the count-th match of the last submitted query after the head of
+    // the first region of the primary selection, or before it if not forward, wrapping around the buffer; the query is
+    // matched with the options it was submitted with, and with the selection scope, only the matches within the
+    // selection it was submitted from are visited
+    pub fn move_to_match(&mut self, buffer: &Buffer, count: usize, forward: bool) {
+        let Some(pattern) = self.search.last_pattern() else {
+            return;
+        };
+        let query = self.search.last_query();
+        let selection = (self.search.last_options().scope() == Scope::Selection).then(|| self.search.last_selection());
+        let len_chars = buffer.len_chars();
+        let Some(mut head) = self.selection_set.primary().iter().next().map(Region::head) else {
+            return;
+        };
 
-        self.close_search();
-        self.search.clear();
+        for _ in 0..count {
+            let next_match = if forward {
+                Self::search_matches(buffer, query, pattern, head.saturating_add(1)..len_chars, selection)
+                    .next()
+                    .or_else(|| Self::search_matches(buffer, query, pattern, 0..len_chars, selection).next())
+            } else {
+                Self::last_search_match(buffer, query, pattern, 0..head, selection)
+                    .or_else(|| Self::last_search_match(buffer, query, pattern, 0..len_chars, selection))
+            };
+            let Some(next_match) = next_match else {
+                return;
+            };
+
+            head = next_match.begin();
+        }
+
+        self.selection_set = Region::unit(head).into();
     }
 
     pub fn close_search(&mut self) {
-        self.context = Context::Buffer;
+        if let Some(origin) = self.search.end() {
+            self.mode = origin.mode;
+            self.selection_set = origin.selection_set;
+            self.position = origin.position;
+        }
+    }
+
+    // NOTE: typed chars are pushed to the search query while the search prompt is open and inserted into the buffer
+    // otherwise
+    pub fn input_char(&mut self, buffer: &mut Buffer, chr: char) {
+        if self.search.is_active() {
+            self.push_search(buffer, chr);
+        } else {
+            self.insert_char(buffer, chr);
+        }
     }
 
     pub fn insert_char(&mut self, buffer: &mut Buffer, chr: char) {
+        let selection_set_before = self.selection_set.clone();
         let selection = self.selection_set.primary_mut();
         let mut new_selection = Selection::default();
         let len_chars = 1;
@@ -163,14 +785,158 @@ impl View {
         }
 
         selection.replace_with(new_selection);
+        buffer.commit(selection_set_before, self.selection_set.clone());
+    }
+
+    // NOTE: each region is moved to the start of the new line, after any auto-indentation
+    pub fn insert_newline(&mut self, buffer: &mut Buffer) {
+        let selection_set_before = self.selection_set.clone();
+        let selection = self.selection_set.primary_mut();
+        let mut new_selection = Selection::default();
+        let mut len_chars_inserted = 0;
+
+        for region in selection.iter() {
+            let insert_idx = region.begin().saturating_add(len_chars_inserted);
+            let newline = buffer.newline(insert_idx);
+            let len_chars = newline.chars().count();
+            let new_region = Region::unit(insert_idx.saturating_add(len_chars));
+
+            buffer.insert(insert_idx, &newline).warn();
+            new_selection.insert(new_region);
+
+            len_chars_inserted = len_chars_inserted.saturating_add(len_chars);
+        }
+
+        selection.replace_with(new_selection);
+        buffer.commit(selection_set_before, self.selection_set.clone());
+    }
+
+    // NOTE: removes the char range returned by `char_range` for each region in the primary selection; all ranges are
+    // computed against the buffer before any removals, so each is corrected by the number of chars already removed for
+    // earlier regions
+    fn remove_each<F: Fn(&Buffer, &Region) -> Range<usize>>(&mut self, buffer: &mut Buffer, char_range: F) {
+        let selection_set_before = self.selection_set.clone();
+        let selection = self.selection_set.primary_mut();
+        let char_ranges = selection
+            .iter()
+            .map(|region| char_range(buffer, region))
+            .collect::<Vec<_>>();
+        let mut new_selection = Selection::default();
+        let mut len_chars_removed = 0;
+        let mut prev_end = 0;
+
+        for Range { start, end } in char_ranges {
+            let start = start.max(prev_end);
+            let end = end.max(start);
+            let remove_begin = start.saturating_sub(len_chars_removed);
+            let remove_end = end.saturating_sub(len_chars_removed);
+            let len_removed = buffer.remove(remove_begin..remove_end).warn().unwrap_or_default();
+            let new_region = Region::unit(remove_begin.min(buffer.len_chars()));
+
+            new_selection.insert(new_region);
+
+            // NOTE: buffer.remove() saturates at the end of the buffer, so only the chars it actually removed offset the
+            // later char ranges
+            len_chars_removed = len_chars_removed.saturating_add(len_removed);
+            prev_end = end;
+        }
+
+        selection.replace_with(new_selection);
+        buffer.commit(selection_set_before, self.selection_set.clone());
+    }
+
+    // NOTE: while the search prompt is open, backspace removes the last char of the query instead
+    pub fn backspace(&mut self, buffer: &mut Buffer, count: usize) {
+        if self.search.is_active() {
+            return self.pop_search(buffer);
+        }
+
+        self.remove_each(buffer, |buffer, region| {
+            Self::nth_grapheme(buffer, region.begin(), false, count)..region.begin()
+        });
+    }
+
+    pub fn delete(&mut self, buffer: &mut Buffer, count: usize) {
+        self.remove_each(buffer, |buffer, region| {
+            region.begin()..Self::nth_grapheme(buffer, region.begin(), true, count)
+        });
+    }
+
+    pub fn delete_selection(&mut self, buffer: &mut Buffer) {
+        self.remove_each(buffer, |_buffer, region| region.begin()..region.end_exclusive());
+    }
+
+    pub fn copy(&self, buffer: &Buffer) -> Vec<String> {
+        self.selection_set
+            .primary()
+            .iter()
+            .map(|region| buffer.text(region.begin()..region.end_exclusive()))
+            .collect()
+    }
+
+    pub fn cut(&mut self, buffer: &mut Buffer) -> Vec<String> {
+        let texts = self.copy(buffer);
+
+        self.delete_selection(buffer);
+
+        texts
+    }
+
+    pub fn num_regions(&self) -> usize {
+        self.selection_set.primary().iter().count()
+    }
+
+    // NOTE: texts[i] is inserted before the i-th region of the primary selection
+    pub fn paste(&mut self, buffer: &mut Buffer, texts: &[String]) {
+        if texts.is_empty() {
+            return;
+        }
+
+        let selection_set_before = self.selection_set.clone();
+        let selection = self.selection_set.primary_mut();
+        let mut new_selection = Selection::default();
+        let mut len_chars_inserted = 0;
+
+        for (region, text) in selection.iter().zip(texts) {
+            let insert_idx = region.begin().saturating_add(len_chars_inserted);
+            let len_chars = text.chars().count();
+            let new_region = Region::unit(insert_idx.saturating_add(len_chars));
+
+            buffer.insert(insert_idx, text).warn();
+            new_selection.insert(new_region);
+
+            len_chars_inserted = len_chars_inserted.saturating_add(len_chars);
+        }
+
+        selection.replace_with(new_selection);
+        buffer.commit(selection_set_before, self.selection_set.clone());
     }
 
-    pub fn save(&self, buffer: &Buffer) -> Result<(), IoError> {
+    pub fn undo(&mut self, buffer: &mut Buffer) -> Result<(), RopeyError> {
+        if let Some(selection_set) = buffer.undo()? {
+            self.selection_set = selection_set;
+        }
+
+        ().ok()
+    }
+
+    pub fn redo(&mut self, buffer: &mut Buffer) -> Result<(), RopeyError> {
+        if let Some(selection_set) = buffer.redo()? {
+            self.selection_set = selection_set;
+        }
+
+        ().ok()
+    }
+
+    pub fn save(&self, buffer: &mut Buffer) -> Result<(), IoError> {
         let Some(filepath) = &self.header.path() else {
             return ().ok();
         };
 
-        filepath.create()?.buf_writer().write_iter(buffer.chunks())?.ok()
+        filepath.create()?.buf_writer().write_iter(buffer.chunks())?;
+        buffer.mark_saved();
+
+        ().ok()
     }
 }
 
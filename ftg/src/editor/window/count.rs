@@ -0,0 +1,59 @@
+use crate::utils::any::Any;
+use crossterm::event::{Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+
+// NOTE: a count is the number of times the next command is repeated; it is built up one digit at a time and begins
+// either with its first digit or with the count key, in which case it is prefixed
+#[derive(Default)]
+pub struct Count {
+    value: Option<usize>,
+    prefixed: bool,
+}
+
+impl Count {
+    const MAX_VALUE: usize = 99_999;
+    const RADIX: u32 = 10;
+
+    // NOTE: only unmodified digit key presses can add to a count
+    pub fn digit(event: &Event) -> Option<usize> {
+        let Event::Key(KeyEvent {
+            code: KeyCode::Char(chr),
+            modifiers: KeyModifiers::NONE,
+            kind: KeyEventKind::Press,
+            ..
+        }) = event
+        else {
+            return None;
+        };
+
+        chr.to_digit(Self::RADIX)?.try_into().ok()
+    }
+
+    pub fn is_prefixed(&self) -> bool {
+        self.prefixed
+    }
+
+    pub fn begin(&mut self) {
+        self.prefixed = true;
+    }
+
+    // NOTE: returns false if the digit can't be added, which is the case for the leading digit 0 so that 0 can still be
+    // bound to a command
+    pub fn push(&mut self, digit: usize) -> bool {
+        let value = match self.value {
+            Some(value) => value.saturating_mul(Self::RADIX as usize).saturating_add(digit),
+            None if digit == 0 => return false,
+            None => digit,
+        };
+
+        self.value = value.min(Self::MAX_VALUE).some();
+
+        true
+    }
+
+    // NOTE: a command without a count runs once
+    pub fn take(&mut self) -> usize {
+        self.prefixed = false;
+
+        self.value.take().unwrap_or(1)
+    }
+}
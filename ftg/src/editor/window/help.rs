@@ -0,0 +1,124 @@
+use crate::{editor::keymap::Keymap, utils::any::Any};
+use ratatui::layout::Rect;
+use std::fmt::Write;
+
+pub enum Row {
+    Mode(String),
+    KeyBinding(String),
+}
+
+// NOTE: the help lists every active key binding grouped by mode, in the order the modes first appear in the keymap;
+// each row shows the keys, command, args and condition of a key binding
+pub struct Help {
+    previous_mode: String,
+    rows: Vec<Row>,
+    filter: String,
+    y: usize,
+}
+
+impl Help {
+    pub fn new(previous_mode: String, keymap: &Keymap) -> Self {
+        let active_key_bindings = keymap.active_key_bindings();
+        let keys_width = active_key_bindings
+            .iter()
+            .map(|active_key_binding| active_key_binding.keys.chars().count())
+            .max()
+            .unwrap_or_default();
+        let mut modes = std::vec![];
+        let mut rows = std::vec![];
+
+        for active_key_binding in active_key_bindings {
+            if !modes.contains(&&active_key_binding.mode) {
+                modes.push(&active_key_binding.mode);
+            }
+        }
+
+        for mode in modes {
+            rows.push(Row::Mode(mode.clone()));
+
+            for active_key_binding in active_key_bindings {
+                if &active_key_binding.mode != mode {
+                    continue;
+                }
+
+                let mut row = std::format!(
+                    "{:keys_width$}  {} {}",
+                    active_key_binding.keys,
+                    active_key_binding.command.name(),
+                    active_key_binding.command.args_str(),
+                );
+
+                if let Some(when) = &active_key_binding.when {
+                    std::write!(row, " when {when}").unit();
+                }
+
+                rows.push(Row::KeyBinding(row.trim_end().to_owned()));
+            }
+        }
+
+        Self {
+            previous_mode,
+            rows,
+            filter: String::new(),
+            y: 0,
+        }
+    }
+
+    pub fn into_previous_mode(self) -> String {
+        self.previous_mode
+    }
+
+    pub fn filter(&self) -> &str {
+        &self.filter
+    }
+
+    pub fn y(&self) -> usize {
+        self.y
+    }
+
+    // NOTE: a key binding row is shown if it contains the filter ignoring case, and a mode row is shown if any of the
+    // key binding rows of its mode are shown
+    pub fn rows(&self) -> Vec<&Row> {
+        let filter = self.filter.to_lowercase();
+        let mut rows = std::vec![];
+        let mut mode_row = None;
+
+        for row in &self.rows {
+            match row {
+                Row::Mode(_mode) => mode_row = row.some(),
+                Row::KeyBinding(key_binding) if key_binding.to_lowercase().contains(&filter) => {
+                    rows.extend(mode_row.take());
+                    rows.push(row);
+                }
+                Row::KeyBinding(_key_binding) => {}
+            }
+        }
+
+        rows
+    }
+
+    pub fn push(&mut self, chr: char) {
+        self.filter.push(chr);
+        self.y = 0;
+    }
+
+    pub fn pop(&mut self) {
+        self.filter.pop();
+        self.y = 0;
+    }
+
+    // NOTE: the first row of the area shows the filter, and the help can be scrolled until its last row is at the bottom
+    // of the area
+    pub fn scroll_down(&mut self, count: usize, area: Rect) {
+        let max_y = self
+            .rows()
+            .len()
+            .saturating_sub(usize::from(area.height).saturating_sub(1));
+
+        self.y = self.y.saturating_add(count).min(max_y);
+    }
+
+    pub fn scroll_up(&mut self, count: usize) {
+        self.y = self.y.saturating_sub(count);
+    }
+}
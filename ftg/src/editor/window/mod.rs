@@ -1,2 +1,6 @@
+pub mod count;
+pub mod help;
+pub mod overlay;
+pub mod palette;
 pub mod project;
 pub mod window;
@@ -0,0 +1,17 @@
+use crate::editor::window::{help::Help, palette::Palette};
+
+// NOTE: an overlay is drawn over the buffer area of a window and is edited in its own mode until it's closed, at which
+// point the active view returns to the mode it was in before
+pub enum Overlay {
+    Help(Help),
+    Palette(Palette),
+}
+
+impl Overlay {
+    pub fn into_previous_mode(self) -> String {
+        match self {
+            Self::Help(help) => help.into_previous_mode(),
+            Self::Palette(palette) => palette.into_previous_mode(),
+        }
+    }
+}
@@ -0,0 +1,294 @@
+use crate::{
+    editor::{
+        command::{ArgSpec, Command, CommandSpec},
+        keymap::Keymap,
+    },
+    utils::any::Any,
+};
+use serde_yaml::{Error as SerdeYamlError, Mapping, Value};
+use std::cmp::Reverse;
+
+pub struct Entry {
+    spec: &'static CommandSpec,
+    keys: String,
+}
+
+impl Entry {
+    const KEYS_SEPARATOR: &'static str = " | ";
+
+    pub fn name(&self) -> &'static str {
+        self.spec.name
+    }
+
+    pub fn description(&self) -> &'static str {
+        self.spec.description
+    }
+
+    pub fn keys(&self) -> &str {
+        &self.keys
+    }
+}
+
+// NOTE: the args of the chosen command are asked for one at a time, in the order of its fields
+struct Arguments {
+    spec: &'static CommandSpec,
+    values: Mapping,
+}
+
+// NOTE: the palette is a prompt that lists every command along with its key bindings in the mode the active view was in
+// when the palette was opened; it first matches the query against the command names, and then asks for each argument of
+// the chosen command
+pub struct Palette {
+    previous_mode: String,
+    entries: Vec<Entry>,
+    query: String,
+    matches: Vec<usize>,
+    selected_idx: usize,
+    arguments: Option<Arguments>,
+    error: Option<String>,
+}
+
+impl Palette {
+    const CONSECUTIVE_BONUS: usize = 2;
+    const WORD_BONUS: usize = 3;
+    const WORD_SEPARATOR: char = '_';
+
+    pub fn new(previous_mode: String, keymap: &Keymap) -> Self {
+        let entries = Command::SPECS
+            .iter()
+            .map(|spec| Entry {
+                spec,
+                keys: keymap.keys_strs(&previous_mode, spec.name).join(Entry::KEYS_SEPARATOR),
+            })
+            .collect();
+        let mut palette = Self {
+            previous_mode,
+            entries,
+            query: String::new(),
+            matches: std::vec![],
+            selected_idx: 0,
+            arguments: None,
+            error: None,
+        };
+
+        palette.update_matches();
+
+        palette
+    }
+
+    pub fn into_previous_mode(self) -> String {
+        self.previous_mode
+    }
+
+    // NOTE: returns the text before the query in the prompt, which names the argument being asked for if any
+    pub fn prompt(&self) -> String {
+        match (&self.arguments, self.arg_spec()) {
+            (Some(arguments), Some(arg_spec)) => {
+                std::format!("{} {} ({}): ", arguments.spec.name, arg_spec.name, arg_spec.ty)
+            }
+            _ => "> ".to_owned(),
+        }
+    }
+
+    pub fn query(&self) -> &str {
+        &self.query
+    }
+
+    // NOTE: no commands are listed while the args of the chosen command are asked for
+    pub fn matches(&self) -> impl Iterator<Item = &Entry> {
+        let matches = if self.arguments.is_some() {
+            &[][..]
+        } else {
+            &self.matches[..]
+        };
+
+        matches.iter().map(|entry_idx| &self.entries[*entry_idx])
+    }
+
+    // NOTE: the error is why the last submitted arg was rejected, which is shown until the query is edited or submitted
+    pub fn error(&self) -> Option<&str> {
+        self.error.as_deref()
+    }
+
+    pub fn selected_idx(&self) -> usize {
+        self.selected_idx
+    }
+
+    pub fn push(&mut self, chr: char) {
+        self.query.push(chr);
+        self.error = None;
+        self.update_matches();
+    }
+
+    pub fn pop(&mut self) {
+        self.query.pop();
+        self.error = None;
+        self.update_matches();
+    }
+
+    pub fn select_next(&mut self) {
+        self.selected_idx = self
+            .selected_idx
+            .saturating_add(1)
+            .min(self.matches.len().saturating_sub(1));
+    }
+
+    pub fn select_previous(&mut self) {
+        self.selected_idx = self.selected_idx.saturating_sub(1);
+    }
+
+    fn arg_spec(&self) -> Option<&'static ArgSpec> {
+        let arguments = self.arguments.as_ref()?;
+
+        arguments.spec.args.get(arguments.values.len())
+    }
+
+    // NOTE: a String arg is taken as is, even if it looks like a number, while args of other types are parsed as yaml
+    // scalars, so `5` is a number and `selection` names a variant
+    fn arg_value(arg_spec: &ArgSpec, arg_str: &str) -> Result<Value, SerdeYamlError> {
+        match arg_spec.ty {
+            "String" => Value::String(arg_str.to_owned()).ok(),
+            "bool" => Value::Bool(arg_str.deserialize_from_yaml()?).ok(),
+            "usize" => Value::Number(arg_str.deserialize_from_yaml::<usize>()?.into()).ok(),
+            _ => arg_str.deserialize_from_yaml(),
+        }
+    }
+
+    // NOTE:
+    // - the first submit chooses the selected command and every later submit gives the next arg of that command; returns
+    //   the command once every one of its args has been given
+    // - an arg that can't be parsed, or that completes args that don't make a command, is rejected and left in the
+    //   query along with the error so that it can be corrected
+    pub fn submit(&mut self) -> Option<Command> {
+        let query = self.query.mem_take();
+
+        self.error = None;
+
+        let arguments = if let Some(arguments) = &mut self.arguments {
+            if let Some(arg_spec) = arguments.spec.args.get(arguments.values.len()) {
+                match Self::arg_value(arg_spec, &query) {
+                    Ok(value) => arguments.values.insert(arg_spec.name.into(), value).unit(),
+                    Err(error) => {
+                        self.query = query;
+                        self.error = error.to_string().some();
+
+                        return None;
+                    }
+                }
+            }
+
+            arguments
+        } else {
+            let entry_idx = self.matches.get(self.selected_idx)?;
+            let arguments = Arguments {
+                spec: self.entries[*entry_idx].spec,
+                values: Mapping::new(),
+            };
+
+            self.arguments.insert(arguments)
+        };
+
+        if arguments.values.len() < arguments.spec.args.len() {
+            return None;
+        }
+
+        let mut command = Mapping::new();
+
+        command.insert(Command::TAG.into(), arguments.spec.name.into());
+
+        if !arguments.values.is_empty() {
+            command.insert(Command::ARGS.into(), arguments.values.clone().into());
+        }
+
+        match serde_yaml::from_value::<Command>(command.into()) {
+            Ok(command) => command.some(),
+            Err(error) => {
+                if let Some(arg_spec) = arguments.spec.args.last() {
+                    arguments.values.shift_remove(arg_spec.name);
+                }
+
+                self.query = query;
+                self.error = error.to_string().some();
+
+                None
+            }
+        }
+    }
+
+    fn update_matches(&mut self) {
+        let mut scored_matches = self
+            .entries
+            .iter()
+            .enumerate()
+            .filter_map(|(entry_idx, entry)| Self::score(&self.query, entry.name()).map(|score| (entry_idx, score)))
+            .collect::<Vec<_>>();
+
+        scored_matches.sort_by_key(|(entry_idx, score)| (Reverse(*score), self.entries[*entry_idx].name().len()));
+
+        self.matches = scored_matches
+            .into_iter()
+            .map(|(entry_idx, _score)| entry_idx)
+            .collect();
+        self.selected_idx = 0;
+    }
+
+    // NOTE: the query matches a name if the chars of the query appear in the name in order, ignoring case; a match
+    // scores higher the more of its chars immediately follow the previous matched char or begin a word of the name
+    fn score(query: &str, name: &str) -> Option<usize> {
+        let mut query_chars = query.chars().peekable();
+        let mut score: usize = 0;
+        let mut previous_chr = None;
+        let mut previous_matched = false;
+
+        for chr in name.chars() {
+            let Some(query_chr) = query_chars.peek() else {
+                break;
+            };
+            let matched = chr.eq_ignore_ascii_case(query_chr);
+
+            if matched {
+                query_chars.next();
+
+                score = score.saturating_add(1);
+
+                if previous_matched {
+                    score = score.saturating_add(Self::CONSECUTIVE_BONUS);
+                }
+
+                if previous_chr.is_none_or(|previous_chr| previous_chr == Self::WORD_SEPARATOR) {
+                    score = score.saturating_add(Self::WORD_BONUS);
+                }
+            }
+
+            previous_chr = chr.some();
+            previous_matched = matched;
+        }
+
+        query_chars.peek().is_none().then_some(score)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::editor::{command::ArgSpec, window::palette::Palette};
+    use serde_yaml::Value;
+
+    fn arg_value(ty: &'static str, arg_str: &str) -> Option<Value> {
+        Palette::arg_value(&ArgSpec { name: "arg", ty }, arg_str).ok()
+    }
+
+    #[test]
+    fn args_are_parsed_according_to_their_type() {
+        assert_eq!(arg_value("String", "5"), Some(Value::String("5".to_owned())));
+        assert_eq!(arg_value("String", "true"), Some(Value::String("true".to_owned())));
+        assert_eq!(arg_value("usize", "5"), Some(Value::Number(5.into())));
+        assert_eq!(arg_value("usize", "five"), None);
+        assert_eq!(arg_value("usize", "-1"), None);
+        assert_eq!(arg_value("bool", "true"), Some(Value::Bool(true)));
+        assert_eq!(arg_value("bool", "5"), None);
+        assert_eq!(
+            arg_value("Scope", "selection"),
+            Some(Value::String("selection".to_owned()))
+        );
+    }
+}
@@ -16,11 +16,15 @@ impl Project {
         self.dirpaths.insert(dirpath);
     }
 
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.dirpaths.iter().filter_map(Path::name)
+    }
+
     pub fn title(&self) -> Option<String> {
         if self.dirpaths.is_empty() {
             None
         } else {
-            self.dirpaths.iter().filter_map(Path::name).join(", ").some()
+            self.names().join(", ").some()
         }
     }
 }
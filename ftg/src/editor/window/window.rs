@@ -1,7 +1,13 @@
 use crate::{
     editor::{
-        buffer::buffer::Buffer, color_scheme::ColorScheme, render::Render, terminal::Terminal, view::view::View,
-        window::project::Project,
+        buffer::buffer::Buffer,
+        color_scheme::ColorScheme,
+        condition::Environment,
+        macros::Recording,
+        render::Render,
+        terminal::Terminal,
+        view::view::View,
+        window::{count::Count, help::Help, overlay::Overlay, palette::Palette, project::Project},
     },
     error::Error,
     utils::{
@@ -10,10 +16,14 @@ use crate::{
         path::Path,
     },
 };
+use crossterm::event::Event;
 use path_clean::PathClean;
 use ratatui::layout::Rect;
 use serde::{Deserialize, Serialize};
-use std::path::{Path as StdPath, PathBuf};
+use std::{
+    path::{Path as StdPath, PathBuf},
+    time::{Duration, Instant},
+};
 use ulid::Ulid;
 
 #[derive(Deserialize, Serialize)]
@@ -47,6 +57,11 @@ pub struct Window {
     active_view_index: usize,
     terminal: Terminal,
     project: Project,
+    pending_events: Vec<Event>,
+    pending_events_instant: Instant,
+    count: Count,
+    recording: Option<Recording>,
+    overlay: Option<Overlay>,
 }
 
 impl Window {
@@ -54,6 +69,11 @@ impl Window {
         let id = Ulid::new();
         let active_view_index = 0;
         let terminal = Terminal::new(terminal_area);
+        let pending_events = Vec::new();
+        let pending_events_instant = Instant::now();
+        let count = Count::default();
+        let recording = None;
+        let overlay = None;
 
         Self {
             id,
@@ -61,6 +81,11 @@ impl Window {
             active_view_index,
             terminal,
             project,
+            pending_events,
+            pending_events_instant,
+            count,
+            recording,
+            overlay,
         }
     }
 
@@ -72,24 +97,117 @@ impl Window {
         self.terminal.resize((width, height).rect())
     }
 
+    // NOTE: pending events are a proper prefix of the events of some key binding that are waiting on the next event
+    pub fn take_pending_events(&mut self) -> Vec<Event> {
+        self.pending_events.mem_take()
+    }
+
+    pub fn set_pending_events(&mut self, pending_events: Vec<Event>) {
+        self.pending_events = pending_events;
+        self.pending_events_instant = Instant::now();
+    }
+
+    // NOTE: returns no events unless the pending events have been waiting on the next event for longer than timeout
+    pub fn take_stale_pending_events(&mut self, timeout: Duration) -> Vec<Event> {
+        if self.pending_events_instant.elapsed() <= timeout {
+            return Vec::new();
+        }
+
+        self.take_pending_events()
+    }
+
+    pub fn count_mut(&mut self) -> &mut Count {
+        &mut self.count
+    }
+
+    pub fn start_recording(&mut self, register: String) {
+        self.recording = Recording::new(register).some();
+    }
+
+    pub fn record(&mut self, event: &Event) {
+        if let Some(recording) = &mut self.recording {
+            recording.push(event);
+        }
+    }
+
+    // NOTE: see Recording
+    pub fn mark_dispatch(&mut self, events: &[Event]) {
+        if self.overlay.is_some() || self.active_view().search().is_active() {
+            return;
+        }
+
+        if let Some(recording) = &mut self.recording {
+            recording.mark_dispatch(events);
+        }
+    }
+
+    pub fn stop_recording(&mut self) -> Option<Recording> {
+        self.recording.take()
+    }
+
+    pub fn palette_mut(&mut self) -> Option<&mut Palette> {
+        match &mut self.overlay {
+            Some(Overlay::Palette(palette)) => palette.some(),
+            _ => None,
+        }
+    }
+
+    pub fn help_mut(&mut self) -> Option<&mut Help> {
+        match &mut self.overlay {
+            Some(Overlay::Help(help)) => help.some(),
+            _ => None,
+        }
+    }
+
+    // NOTE: opening an overlay closes the one that's already open, if any
+    pub fn open_overlay(&mut self, mode: String, overlay: Overlay) {
+        self.close_overlay();
+        self.active_view().set_mode(mode);
+        self.overlay = overlay.some();
+    }
+
+    pub fn close_overlay(&mut self) {
+        if let Some(overlay) = self.overlay.take() {
+            self.active_view().set_mode(overlay.into_previous_mode());
+        }
+    }
+
+    pub fn buffer_area(&self) -> Rect {
+        Render::buffer_area(self.terminal.area())
+    }
+
     pub fn active_view(&mut self) -> &mut View {
         &mut self.views[self.active_view_index]
     }
 
+    // NOTE: the state of the active view that the conditions of key bindings are evaluated against
+    pub fn environment<'a>(&'a self, buffers: &'a Container<Buffer>) -> Result<Environment<'a>, Error> {
+        let view = &self.views[self.active_view_index];
+        let environment = Environment {
+            header: view.header(),
+            project: &self.project,
+            selection: view.selection_set().primary(),
+            dirty: buffers.get(&view.buffer_id())?.is_dirty(),
+        };
+
+        environment.ok()
+    }
+
     // TODO: come up w a better implementation that doesn't do this casting
     #[allow(clippy::cast_possible_wrap)]
     fn change_view(&mut self, change: isize) {
         self.active_view_index = change
+            .rem_euclid(self.views.len() as isize)
             .saturating_add_unsigned(self.active_view_index)
             .rem_euclid(self.views.len() as isize) as usize;
     }
 
-    pub fn next_view(&mut self) {
-        self.change_view(1);
+    pub fn next_view(&mut self, count: usize) {
+        self.change_view(count.try_into().unwrap_or(isize::MAX));
     }
 
-    pub fn previous_view(&mut self) {
-        self.change_view(-1);
+    pub fn previous_view(&mut self, count: usize) {
+        self.change_view(-count.try_into().unwrap_or(isize::MAX));
     }
 
     pub fn render(&mut self, buffers: &Container<Buffer>, color_scheme: &ColorScheme) -> Result<Vec<u8>, Error> {
@@ -98,6 +216,7 @@ impl Window {
             &self.views,
             self.active_view_index,
             &self.project,
+            self.overlay.as_ref(),
             buffers,
             color_scheme,
         )?
@@ -1,7 +1,9 @@
 use derive_more::{Display, From};
 use http::{header::InvalidHeaderValue, Error as HttpError};
 use postcard::Error as PostcardError;
+use regex::Error as RegexError;
 use reqwest::Error as ReqwestError;
+use ropey::Error as RopeyError;
 use serde_json::Error as SerdeJsonError;
 use serde_yaml::Error as SerdeYamlError;
 use std::io::Error as IoError;
@@ -18,7 +20,9 @@ pub enum Error {
     InvalidHeaderValue(InvalidHeaderValue),
     Io(IoError),
     Postcard(PostcardError),
+    Regex(RegexError),
     Reqwest(ReqwestError),
+    Ropey(RopeyError),
     SerdeJson(SerdeJsonError),
     SerdeYaml(SerdeYamlError),
     TokioJoin(TokioJoinError),
@@ -26,4 +30,27 @@ pub enum Error {
 
     #[display(fmt = "unknown {_0} ID {_1}")]
     UnknownItem(String, Ulid),
+
+    #[display(fmt = "unknown {_0} {_1:?}")]
+    #[from(ignore)]
+    UnknownName(&'static str, String),
+
+    #[display(fmt = "no modes are declared")]
+    NoModes,
+
+    #[display(fmt = "{_0}: unknown mode {_1:?}")]
+    #[from(ignore)]
+    UnknownMode(String, String),
+
+    #[display(fmt = "composite command {_0:?} can't run in mode {_1:?}")]
+    #[from(ignore)]
+    CompositeMode(String, String),
+
+    #[display(fmt = "composite command {_0:?} failed at commands[{_1}]: {_2}")]
+    #[from(ignore)]
+    CompositeStep(String, usize, Box<Error>),
+
+    #[display(fmt = "commands are nested more than {_0} deep")]
+    #[from(ignore)]
+    NestingDepth(usize),
 }
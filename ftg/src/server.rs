@@ -28,6 +28,7 @@ impl Server {
     const API_PATH: &'static str = "/";
     const API_TITLE: &'static str = std::env!("CARGO_PKG_NAME");
     const API_VERSION: &'static str = std::env!("CARGO_PKG_VERSION");
+    const DEFAULT_CONFIG_NAME: &'static str = "config.yaml";
     const DEFAULT_CONFIG_STR: &'static str = std::include_str!("config.yaml");
     // TODO: resolve
     // pub const WINDOW_ARGS_HEADER_NAME: &'static str = "x-ftg-window-args";
@@ -48,15 +49,14 @@ impl Server {
         poem_server.run(route).await?.ok()
     }
 
-    fn config(cli_args: &CliArgs) -> Result<Config, Error> {
-        // TODO: why is the turbofish necessary
+    pub fn config(cli_args: &CliArgs) -> Result<Config, Error> {
         if let Some(config_filepath) = &cli_args.config_filepath {
-            config_filepath
-                .read_to_string()?
-                .deserialize_from_yaml::<Config>()?
-                .ok()
+            let source_name = config_filepath.display().to_string();
+            let source = config_filepath.read_to_string()?;
+
+            Config::new(&source_name, &source)?.ok()
         } else {
-            Self::DEFAULT_CONFIG_STR.deserialize_from_yaml::<Config>()?.ok()
+            Config::new(Self::DEFAULT_CONFIG_NAME, Self::DEFAULT_CONFIG_STR)?.ok()
         }
     }
 
@@ -87,6 +87,11 @@ impl Server {
                     }
                 }
                 () = std::future::ready(()) => {
+                    // NOTE: a command failing when the pending events time out is logged rather than ending the session
+                    if editor.lock().flush_pending_events(&window_id).warn().unwrap_or(false) {
+                        break;
+                    }
+
                     let bytes = editor.lock().render(&window_id)?;
 
                     if bytes.is_empty() {
@@ -5,12 +5,7 @@ use num_traits::Num;
 use parking_lot::Mutex;
 use poem::web::websocket::Message as PoemMessage;
 use postcard::Error as PostcardError;
-use ratatui::{
-    layout::Rect,
-    style::Stylize,
-    text::{Span, Text},
-    widgets::Paragraph,
-};
+use ratatui::{layout::Rect, style::Stylize, text::Text, widgets::Paragraph};
 use ropey::Rope;
 use serde::{Deserialize, Serialize};
 use serde_json::Error as SerdeJsonError;
@@ -219,13 +214,6 @@ pub trait Any: Sized {
         Paragraph::new(self.into())
     }
 
-    fn push_all_to(self, values: &mut Vec<Self::Item>)
-    where
-        Self: IntoIterator,
-    {
-        values.extend(self);
-    }
-
     fn read_to_string(&self) -> Result<String, IoError>
     where
         Self: AsRef<Path>,
@@ -289,13 +277,6 @@ pub trait Any: Sized {
         Some(self)
     }
 
-    fn spans<'a>(&mut self, count: usize) -> impl Iterator<Item = Span<'a>>
-    where
-        Self: Iterator<Item = &'a str>,
-    {
-        self.take(count).map(Span::raw)
-    }
-
     fn unit(self) {}
 
     fn warn<T, E: Display>(self) -> Option<T>
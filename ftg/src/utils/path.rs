@@ -26,6 +26,10 @@ impl Path {
             .unwrap_or(Self::INVALID_UNICODE_NAME)
             .some()
     }
+
+    pub fn extension(&self) -> Option<&str> {
+        self.path.extension()?.to_str()
+    }
 }
 
 impl AsRef<StdPath> for Path {
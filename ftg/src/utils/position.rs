@@ -1,11 +1,12 @@
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub struct Position {
     pub x: usize,
     pub y: usize,
+    pub sub_y: usize,
 }
 
 impl Position {
     pub fn zero() -> Self {
-        Self { x: 0, y: 0 }
+        Self { x: 0, y: 0, sub_y: 0 }
     }
 }